@@ -0,0 +1,122 @@
+use anyhow::Context;
+
+/// Extensions of the audio files whose headers are read.
+pub const AUDIO_EXT: &[&str] = &["ogg", "wav"];
+
+/// What the headers of an audio file say about it.
+pub struct AudioInfo {
+    pub format: &'static str,
+    /// Length in seconds.
+    pub duration: f64,
+    pub channels: u32,
+    pub sample_rate: u32,
+}
+
+impl AudioInfo {
+    pub fn parse(ext: &str, data: &[u8]) -> anyhow::Result<Self> {
+        match ext {
+            "wav" => parse_wav(data),
+            "ogg" => parse_ogg(data),
+            _ => anyhow::bail!("{ext} is not an audio format"),
+        }
+    }
+}
+
+/// Reads the `fmt ` and `data` chunks of a RIFF wave file.
+fn parse_wav(data: &[u8]) -> anyhow::Result<AudioInfo> {
+    if data.get(..4) != Some(b"RIFF") || data.get(8..12) != Some(b"WAVE") {
+        anyhow::bail!("not a RIFF wave file");
+    }
+    let mut format = None;
+    let mut data_size = None;
+    let mut pos = 12;
+    while let Some(header) = data.get(pos..pos + 8) {
+        let size = u32_at(header, 4)? as usize;
+        let body = &data[pos + 8..];
+        match &header[..4] {
+            b"fmt " => {
+                let tag = u16::from_le_bytes(body.get(..2).context("truncated fmt")?.try_into()?);
+                let channels =
+                    u16::from_le_bytes(body.get(2..4).context("truncated fmt")?.try_into()?);
+                let sample_rate = u32_at(body, 4)?;
+                let byte_rate = u32_at(body, 8)?;
+                format = Some((tag, channels as u32, sample_rate, byte_rate));
+            }
+            // the size of a data chunk that was still being written may run past the end
+            b"data" => data_size = Some(size.min(body.len())),
+            _ => {}
+        }
+        // chunks are padded to an even size
+        pos += 8 + size + size % 2;
+    }
+    let (tag, channels, sample_rate, byte_rate) = format.context("missing fmt chunk")?;
+    let data_size = data_size.context("missing data chunk")?;
+    if byte_rate == 0 {
+        anyhow::bail!("invalid byte rate 0");
+    }
+    Ok(AudioInfo {
+        format: match tag {
+            1 => "pcm",
+            2 => "adpcm",
+            3 => "float",
+            0x11 => "imaadpcm",
+            0xfffe => "extensible",
+            _ => "unknown",
+        },
+        duration: data_size as f64 / byte_rate as f64,
+        channels,
+        sample_rate,
+    })
+}
+
+/// Reads the identification header in the first page of an Ogg Vorbis or Opus stream, and the
+/// granule position of the last page.
+fn parse_ogg(data: &[u8]) -> anyhow::Result<AudioInfo> {
+    if data.get(..4) != Some(b"OggS") {
+        anyhow::bail!("not an Ogg file");
+    }
+    let segments = *data.get(26).context("truncated page")? as usize;
+    let packet = data.get(27 + segments..).context("truncated page")?;
+    // Opus granule positions always count 48 kHz samples, after skipping the encoder delay
+    let (format, channels, sample_rate, granule_rate, pre_skip) =
+        if packet.starts_with(b"\x01vorbis") {
+            let channels = *packet.get(11).context("truncated vorbis header")? as u32;
+            let sample_rate = u32_at(packet, 12)?;
+            ("vorbis", channels, sample_rate, sample_rate, 0)
+        } else if packet.starts_with(b"OpusHead") {
+            let channels = *packet.get(9).context("truncated opus header")? as u32;
+            let pre_skip = u16::from_le_bytes(
+                packet
+                    .get(10..12)
+                    .context("truncated opus header")?
+                    .try_into()?,
+            );
+            let sample_rate = u32_at(packet, 12)?;
+            ("opus", channels, sample_rate, 48000, pre_skip as u64)
+        } else {
+            anyhow::bail!("unsupported Ogg codec");
+        };
+    if granule_rate == 0 {
+        anyhow::bail!("invalid sample rate 0");
+    }
+
+    let last_page = data
+        .windows(4)
+        .rposition(|w| w == b"OggS")
+        .context("missing last page")?;
+    let granule = data
+        .get(last_page + 6..last_page + 14)
+        .context("truncated last page")?;
+    let granule = u64::from_le_bytes(granule.try_into()?);
+    Ok(AudioInfo {
+        format,
+        duration: granule.saturating_sub(pre_skip) as f64 / granule_rate as f64,
+        channels,
+        sample_rate,
+    })
+}
+
+fn u32_at(data: &[u8], at: usize) -> anyhow::Result<u32> {
+    let bytes = data.get(at..at + 4).context("unexpected end of file")?;
+    Ok(u32::from_le_bytes(bytes.try_into()?))
+}
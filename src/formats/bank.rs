@@ -0,0 +1,255 @@
+use crate::formats::reader::Reader;
+use anyhow::Context;
+use std::collections::HashSet;
+
+/// Magic of the FMOD sound banks embedded in a `.bank` file.
+const FSB5: &[u8; 4] = b"FSB5";
+
+/// Sample rates selected by the frequency bits of a sample header.
+const FREQUENCIES: [u32; 10] = [
+    0, 8000, 11000, 11025, 16000, 22050, 24000, 32000, 44100, 48000,
+];
+
+const CHUNK_CHANNELS: u32 = 1;
+const CHUNK_FREQUENCY: u32 = 2;
+
+/// A sound embedded in an FMOD bank.
+pub struct Sound {
+    /// From the name table, or numbered when the bank was built without names.
+    pub name: String,
+    pub format: &'static str,
+    pub channels: u32,
+    pub sample_rate: u32,
+    pub samples: u64,
+    /// Length of the encoded data in bytes.
+    pub size: u64,
+}
+
+impl Sound {
+    /// Length in seconds.
+    pub fn duration(&self) -> f64 {
+        if self.sample_rate == 0 {
+            return 0.0;
+        }
+        self.samples as f64 / self.sample_rate as f64
+    }
+}
+
+/// Lists the sounds of every sound bank embedded in an FMOD `.bank` file.
+pub fn sounds(data: &[u8]) -> anyhow::Result<Vec<Sound>> {
+    let mut sounds = Vec::new();
+    let mut names = HashSet::new();
+    let mut found = false;
+    let mut pos = 0;
+    while let Some(start) = data[pos..].windows(4).position(|w| w == FSB5) {
+        let start = pos + start;
+        let (bank, end) =
+            parse_fsb(&data[start..]).with_context(|| format!("sound bank at offset {start}"))?;
+        for mut sound in bank {
+            // names are unique within a bank but not across the banks of one file
+            if !names.insert(sound.name.clone()) {
+                sound.name = format!("{}_{}", sound.name, names.len());
+                names.insert(sound.name.clone());
+            }
+            sounds.push(sound);
+        }
+        found = true;
+        pos = start + end.max(FSB5.len());
+    }
+    if !found {
+        anyhow::bail!("no FSB5 sound bank found");
+    }
+    Ok(sounds)
+}
+
+/// Parses one FSB5 bank, returning its sounds and its length.
+fn parse_fsb(data: &[u8]) -> anyhow::Result<(Vec<Sound>, usize)> {
+    let r = &mut Reader::new(data);
+    r.skip(FSB5.len())?;
+    let version = r.u32()?;
+    let count = r.u32()? as usize;
+    let headers_size = r.u32()? as usize;
+    let names_size = r.u32()? as usize;
+    let data_size = r.u32()? as usize;
+    let mode = r.u32()?;
+    // version 0 has one more unknown field before the flags, hash and padding
+    let header_size = if version == 0 { 64 } else { 60 };
+    let names_start = header_size + headers_size;
+    let data_start = names_start + names_size;
+    let end = data_start + data_size;
+    if end > data.len() {
+        anyhow::bail!("bank of {end} bytes does not fit in {} bytes", data.len());
+    }
+    // every sample header takes at least 8 bytes
+    if count.checked_mul(8).is_none_or(|size| size > headers_size) {
+        anyhow::bail!("{count} sample headers do not fit in {headers_size} bytes");
+    }
+
+    let r = &mut Reader::new(&data[header_size..names_start]);
+    let mut headers = Vec::with_capacity(count);
+    for _ in 0..count {
+        let raw = r.u64()?;
+        let mut channels = ((raw >> 5) & 1) as u32 + 1;
+        let mut sample_rate = FREQUENCIES
+            .get(((raw >> 1) & 0xf) as usize)
+            .copied()
+            .unwrap_or_default();
+        let offset = ((raw >> 6) & 0x0fff_ffff) as usize * 16;
+        let samples = (raw >> 34) & 0x3fff_ffff;
+        let mut next = raw & 1 == 1;
+        while next {
+            let chunk = r.u32()?;
+            next = chunk & 1 == 1;
+            let size = ((chunk >> 1) & 0xff_ffff) as usize;
+            let body = &mut Reader::new(r.bytes(size)?);
+            match chunk >> 25 {
+                CHUNK_CHANNELS => channels = body.u8()? as u32,
+                CHUNK_FREQUENCY => sample_rate = body.u32()?,
+                _ => {}
+            }
+        }
+        headers.push((offset, channels, sample_rate, samples));
+    }
+
+    let names = &data[names_start..data_start];
+    let mut sounds = Vec::with_capacity(count);
+    for (i, &(offset, channels, sample_rate, samples)) in headers.iter().enumerate() {
+        let next = headers.get(i + 1).map_or(data_size, |h| h.0);
+        let name = (names_size > 0)
+            .then(|| name(names, i))
+            .transpose()?
+            .unwrap_or_else(|| format!("sound_{i:04}"));
+        sounds.push(Sound {
+            name,
+            format: codec(mode),
+            channels,
+            sample_rate,
+            samples,
+            size: next.saturating_sub(offset) as u64,
+        });
+    }
+    Ok((sounds, end))
+}
+
+/// Reads the `i`th name of a name table, which starts with the offsets of its strings.
+fn name(names: &[u8], i: usize) -> anyhow::Result<String> {
+    let offset = Reader::new(names.get(i * 4..).context("truncated name table")?).u32()? as usize;
+    let name = names.get(offset..).context("name offset out of bounds")?;
+    let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    Ok(String::from_utf8_lossy(&name[..len]).to_lowercase())
+}
+
+fn codec(mode: u32) -> &'static str {
+    match mode {
+        1 => "pcm8",
+        2 => "pcm16",
+        3 => "pcm24",
+        4 => "pcm32",
+        5 => "pcmfloat",
+        6 => "gcadpcm",
+        7 => "imaadpcm",
+        8 => "vag",
+        9 => "hevag",
+        10 => "xma",
+        11 => "mpeg",
+        12 => "celt",
+        13 => "at9",
+        14 => "xwma",
+        15 => "vorbis",
+        16 => "fadpcm",
+        17 => "opus",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a version 1 FSB5 bank of vorbis sounds from raw sample headers and names.
+    fn fsb(count: u32, headers: &[u8], names: &[&str], data_size: u32) -> Vec<u8> {
+        let mut table = Vec::new();
+        let mut strings = Vec::new();
+        for name in names {
+            table.extend((names.len() as u32 * 4 + strings.len() as u32).to_le_bytes());
+            strings.extend(name.as_bytes());
+            strings.push(0);
+        }
+        table.extend(strings);
+
+        let mut bank = FSB5.to_vec();
+        for field in [
+            1,
+            count,
+            headers.len() as u32,
+            table.len() as u32,
+            data_size,
+            15,
+        ] {
+            bank.extend(field.to_le_bytes());
+        }
+        bank.resize(60, 0);
+        bank.extend(headers);
+        bank.extend(table);
+        bank.resize(bank.len() + data_size as usize, 0);
+        bank
+    }
+
+    fn sample(frequency: u64, stereo: bool, offset: u64, samples: u64, chunks: bool) -> u64 {
+        chunks as u64 | frequency << 1 | (stereo as u64) << 5 | (offset / 16) << 6 | samples << 34
+    }
+
+    #[test]
+    fn reads_sample_headers_and_names() {
+        let mut headers = sample(8, true, 0, 44100, false).to_le_bytes().to_vec();
+        headers.extend(sample(9, false, 32, 96000, true).to_le_bytes());
+        headers.extend((CHUNK_CHANNELS << 25 | 1 << 1).to_le_bytes());
+        headers.push(6);
+        let mut data = b"RIFF\0\0\0\0".to_vec();
+        data.extend(fsb(2, &headers, &["Foo", "Bar"], 48));
+
+        let sounds = sounds(&data).unwrap();
+        assert_eq!(sounds.len(), 2);
+        let (foo, bar) = (&sounds[0], &sounds[1]);
+        assert_eq!(
+            (foo.name.as_str(), foo.format, foo.channels, foo.sample_rate),
+            ("foo", "vorbis", 2, 44100)
+        );
+        assert_eq!((foo.samples, foo.size, foo.duration()), (44100, 32, 1.0));
+        assert_eq!(
+            (bar.name.as_str(), bar.channels, bar.sample_rate),
+            ("bar", 6, 48000)
+        );
+        assert_eq!((bar.samples, bar.size, bar.duration()), (96000, 16, 2.0));
+    }
+
+    #[test]
+    fn renames_duplicates_across_banks() {
+        let headers = sample(8, false, 0, 100, false).to_le_bytes();
+        let mut data = fsb(1, &headers, &["Foo"], 16);
+        data.extend(fsb(1, &headers, &["Foo"], 16));
+
+        let names = sounds(&data)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["foo", "foo_1"]);
+    }
+
+    #[test]
+    fn numbers_unnamed_sounds() {
+        let headers = sample(8, false, 0, 100, false).to_le_bytes();
+        let sounds = sounds(&fsb(1, &headers, &[], 16)).unwrap();
+        assert_eq!(sounds[0].name, "sound_0000");
+    }
+
+    #[test]
+    fn rejects_truncated_and_missing_banks() {
+        let headers = sample(8, false, 0, 100, false).to_le_bytes();
+        let bank = fsb(1, &headers, &["Foo"], 16);
+        assert!(sounds(&bank[..bank.len() - 1]).is_err());
+        assert!(sounds(b"RIFF not a bank").is_err());
+        assert!(sounds(&fsb(u32::MAX, &headers, &["Foo"], 16)).is_err());
+    }
+}
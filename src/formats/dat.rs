@@ -0,0 +1,403 @@
+use anyhow::Context;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Separates the fixed-width rows of a dat file from its variable data section.
+const MAGIC: [u8; 8] = [0xBB; 8];
+/// Written in `row` and `foreignrow` columns that point nowhere.
+const NULL_ROW: u64 = 0xFEFE_FEFE_FEFE_FEFE;
+
+/// Column definitions in the format of the community `schema.min.json`.
+#[derive(Deserialize)]
+pub struct Schema {
+    pub tables: Vec<Table>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Table {
+    pub name: String,
+    /// Bit set of the games the table exists in: 1 for poe1, 2 for poe2.
+    #[serde(default = "both_games")]
+    pub valid_for: u8,
+    pub columns: Vec<Column>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Column {
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub typ: String,
+    #[serde(default)]
+    pub array: bool,
+    /// Intervals store two values of the column's type, e.g. a min and max.
+    #[serde(default)]
+    pub interval: bool,
+    /// Whether every row has a different value, which makes the column usable as a key.
+    #[serde(default)]
+    pub unique: bool,
+    pub references: Option<Reference>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Reference {
+    pub table: String,
+}
+
+fn both_games() -> u8 {
+    3
+}
+
+fn game(storage: Option<&str>) -> u8 {
+    match storage {
+        Some("poe1") => 1,
+        Some("poe2") => 2,
+        _ => both_games(),
+    }
+}
+
+impl Schema {
+    /// Loads the schema file named by `DAT_SCHEMA`, if any.
+    pub fn from_env() -> Option<Arc<Self>> {
+        let path = std::env::var("DAT_SCHEMA").ok()?;
+        Self::load(Path::new(&path))
+            .map(Arc::new)
+            .inspect_err(|e| eprintln!("Failed to load dat schema {path}: {e:?}"))
+            .ok()
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Finds the table stored in `data/<name>.datc64`, preferring the one valid for `storage`.
+    pub fn table(&self, name: &str, storage: Option<&str>) -> Option<&Table> {
+        let game = game(storage);
+        let mut tables = self
+            .tables
+            .iter()
+            .filter(|t| t.name.eq_ignore_ascii_case(name));
+        let first = tables.next()?;
+        if first.valid_for & game != 0 {
+            return Some(first);
+        }
+        tables.find(|t| t.valid_for & game != 0).or(Some(first))
+    }
+
+    /// Every table that exists in `storage`.
+    pub fn tables_for<'a>(&'a self, storage: Option<&str>) -> impl Iterator<Item = &'a Table> {
+        let game = game(storage);
+        self.tables.iter().filter(move |t| t.valid_for & game != 0)
+    }
+
+    /// The table name of a dat file path, e.g. `BaseItemTypes` for `data/baseitemtypes.datc64`.
+    pub fn table_name(path: &str) -> Option<&str> {
+        let name = path.rsplit('/').next()?;
+        name.strip_suffix(".datc64")
+    }
+}
+
+impl Table {
+    /// Column names, with unnamed columns called after their position.
+    pub fn column_names(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(i, c)| c.name.clone().unwrap_or_else(|| format!("Unknown{i}")))
+            .collect()
+    }
+
+    /// Offset of each column within a row.
+    pub fn offsets(&self) -> Vec<usize> {
+        self.columns
+            .iter()
+            .scan(0, |offset, c| {
+                let start = *offset;
+                *offset += c.width();
+                Some(start)
+            })
+            .collect()
+    }
+
+    pub fn width(&self) -> usize {
+        self.columns.iter().map(Column::width).sum()
+    }
+
+    /// The column identifying rows across versions: `Id` if it is unique, or else the first
+    /// unique column.
+    pub fn key_column(&self) -> Option<usize> {
+        let unique = || self.columns.iter().enumerate().filter(|(_, c)| c.unique);
+        unique()
+            .find(|(_, c)| c.name.as_deref() == Some("Id"))
+            .or_else(|| unique().next())
+            .map(|(i, _)| i)
+    }
+}
+
+impl Column {
+    /// The table whose row indexes this column holds, given the table the column belongs to.
+    /// Keys matched by value rather than by row index are not followed.
+    pub fn referenced_table<'a>(&'a self, own: &'a str) -> Option<&'a str> {
+        let referenced = self.references.as_ref().map(|r| r.table.as_str());
+        match self.typ.as_str() {
+            "foreignrow" => referenced,
+            "row" => Some(referenced.unwrap_or(own)),
+            _ => None,
+        }
+    }
+
+    /// Number of bytes the column takes in a row.
+    pub fn width(&self) -> usize {
+        if self.array {
+            16
+        } else if self.interval {
+            2 * scalar_width(&self.typ)
+        } else {
+            scalar_width(&self.typ)
+        }
+    }
+}
+
+fn scalar_width(typ: &str) -> usize {
+    match typ {
+        "bool" | "i8" | "u8" => 1,
+        "i16" | "u16" => 2,
+        "i32" | "u32" | "f32" | "enumrow" => 4,
+        "string" | "row" | "i64" | "u64" | "f64" => 8,
+        "foreignrow" | "array" => 16,
+        _ => 0,
+    }
+}
+
+/// A `.datc64` table: a row count, fixed-width rows and a variable data section that strings
+/// and arrays point into.
+pub struct Dat<'a> {
+    pub row_count: usize,
+    pub row_width: usize,
+    rows: &'a [u8],
+    data: &'a [u8],
+}
+
+impl<'a> Dat<'a> {
+    pub fn parse(bytes: &'a [u8]) -> anyhow::Result<Self> {
+        let row_count = bytes
+            .get(..4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+            .context("dat file too short")?;
+        let magic = bytes[4..]
+            .windows(MAGIC.len())
+            .position(|w| w == MAGIC)
+            .context("dat file has no variable data section")?
+            + 4;
+        let rows = &bytes[4..magic];
+        let row_width = match row_count {
+            0 => 0,
+            n if rows.len().is_multiple_of(n) => rows.len() / n,
+            n => anyhow::bail!("{} bytes of rows is not a multiple of {n} rows", rows.len()),
+        };
+        Ok(Self {
+            row_count,
+            row_width,
+            rows,
+            data: &bytes[magic..],
+        })
+    }
+
+    pub fn row(&self, index: usize) -> anyhow::Result<&'a [u8]> {
+        if index >= self.row_count {
+            anyhow::bail!("row {index} out of bounds for {} rows", self.row_count);
+        }
+        Ok(&self.rows[index * self.row_width..(index + 1) * self.row_width])
+    }
+
+    /// Reads the value of `column`, which starts at `offset` within the row.
+    pub fn read(&self, row: usize, column: &Column, offset: usize) -> anyhow::Result<Value> {
+        let bytes = self
+            .row(row)?
+            .get(offset..offset + column.width())
+            .with_context(|| format!("column at {offset} exceeds row width {}", self.row_width))?;
+        if column.array {
+            let count = u64_at(bytes, 0) as usize;
+            let start = u64_at(bytes, 8) as usize;
+            let width = scalar_width(&column.typ);
+            if column.typ == "array" || width == 0 {
+                return Ok(Value::Null);
+            }
+            let elements = count
+                .checked_mul(width)
+                .and_then(|len| self.data.get(start..start.checked_add(len)?))
+                .with_context(|| format!("array of {count} at {start} out of bounds"))?;
+            elements
+                .chunks_exact(width)
+                .map(|b| self.scalar(&column.typ, b))
+                .collect()
+        } else if column.interval {
+            let (min, max) = bytes.split_at(bytes.len() / 2);
+            Ok(Value::Array(vec![
+                self.scalar(&column.typ, min)?,
+                self.scalar(&column.typ, max)?,
+            ]))
+        } else {
+            self.scalar(&column.typ, bytes)
+        }
+    }
+
+    /// Reads the given columns of a row, with `offsets` as returned by [`Table::offsets`].
+    pub fn read_columns(
+        &self,
+        row: usize,
+        table: &Table,
+        columns: &[usize],
+        offsets: &[usize],
+    ) -> anyhow::Result<Vec<Value>> {
+        columns
+            .iter()
+            .map(|&i| self.read(row, &table.columns[i], offsets[i]))
+            .collect()
+    }
+
+    fn scalar(&self, typ: &str, b: &[u8]) -> anyhow::Result<Value> {
+        Ok(match typ {
+            "bool" => Value::from(b[0] != 0),
+            "i8" => Value::from(b[0] as i8),
+            "u8" => Value::from(b[0]),
+            "i16" => Value::from(i16::from_le_bytes([b[0], b[1]])),
+            "u16" => Value::from(u16::from_le_bytes([b[0], b[1]])),
+            "i32" => Value::from(i32::from_le_bytes(b[..4].try_into()?)),
+            "u32" | "enumrow" => Value::from(u32::from_le_bytes(b[..4].try_into()?)),
+            "f32" => Value::from(f32::from_le_bytes(b[..4].try_into()?)),
+            "i64" => Value::from(i64::from_le_bytes(b[..8].try_into()?)),
+            "u64" => Value::from(u64_at(b, 0)),
+            "f64" => Value::from(f64::from_le_bytes(b[..8].try_into()?)),
+            "string" => Value::from(self.string(u64_at(b, 0) as usize)?),
+            "row" | "foreignrow" => match u64_at(b, 0) {
+                NULL_ROW => Value::Null,
+                row => Value::from(row),
+            },
+            _ => Value::Null,
+        })
+    }
+
+    /// Reads the NUL-terminated UTF-16LE string at `offset` in the variable data section.
+    fn string(&self, offset: usize) -> anyhow::Result<String> {
+        let data = self
+            .data
+            .get(offset..)
+            .with_context(|| format!("string at {offset} out of bounds"))?;
+        let units = data
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|&u| u != 0)
+            .collect::<Vec<_>>();
+        Ok(String::from_utf16_lossy(&units))
+    }
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn table() -> Table {
+        serde_json::from_value(json!({
+            "name": "Things",
+            "columns": [
+                { "name": "Id", "type": "string", "unique": true },
+                { "name": "Level", "type": "i32" },
+                { "name": "Range", "type": "i32", "interval": true },
+                { "name": "Tags", "type": "u16", "array": true },
+                { "name": "Parent", "type": "row" },
+                { "name": "Other", "type": "foreignrow", "references": { "table": "Others" } },
+                { "type": "bool" },
+            ],
+        }))
+        .unwrap()
+    }
+
+    /// Two rows, followed by a variable data section holding the string "Ab" and the array
+    /// `[1, 2, 3]`.
+    fn dat() -> Vec<u8> {
+        let mut bytes = 2u32.to_le_bytes().to_vec();
+        let rows: [(i32, [i32; 2], u64, u64, u64, bool); 2] = [
+            (5, [1, 10], 3, NULL_ROW, 1, true),
+            (-1, [-2, 2], 0, 0, NULL_ROW, false),
+        ];
+        for (level, range, tags, parent, other, flag) in rows {
+            bytes.extend(8u64.to_le_bytes());
+            bytes.extend(level.to_le_bytes());
+            bytes.extend(range.iter().flat_map(|v| v.to_le_bytes()));
+            bytes.extend(tags.to_le_bytes());
+            bytes.extend(14u64.to_le_bytes());
+            bytes.extend(parent.to_le_bytes());
+            bytes.extend(other.to_le_bytes());
+            bytes.extend([0; 8]);
+            bytes.push(flag as u8);
+        }
+        bytes.extend(MAGIC);
+        bytes.extend([b'A', 0, b'b', 0, 0, 0]);
+        bytes.extend([1u16, 2, 3].iter().flat_map(|v| v.to_le_bytes()));
+        bytes
+    }
+
+    #[test]
+    fn reads_every_column_type() {
+        let (table, bytes) = (table(), dat());
+        let dat = Dat::parse(&bytes).unwrap();
+        assert_eq!((dat.row_count, dat.row_width), (2, table.width()));
+
+        let all = (0..table.columns.len()).collect::<Vec<_>>();
+        let offsets = table.offsets();
+        assert_eq!(
+            dat.read_columns(0, &table, &all, &offsets).unwrap(),
+            [
+                json!("Ab"),
+                json!(5),
+                json!([1, 10]),
+                json!([1, 2, 3]),
+                Value::Null,
+                json!(1),
+                json!(true),
+            ]
+        );
+        assert_eq!(
+            dat.read_columns(1, &table, &all, &offsets).unwrap(),
+            [
+                json!("Ab"),
+                json!(-1),
+                json!([-2, 2]),
+                json!([]),
+                json!(0),
+                Value::Null,
+                json!(false),
+            ]
+        );
+        assert!(dat.read(2, &table.columns[0], 0).is_err());
+    }
+
+    #[test]
+    fn rejects_values_outside_the_data_section() {
+        let table = table();
+        let mut bytes = dat();
+        // point the first row's array past the end of the data section
+        bytes[4 + 28..4 + 36].copy_from_slice(&100u64.to_le_bytes());
+        let dat = Dat::parse(&bytes).unwrap();
+        assert!(dat.read(0, &table.columns[3], table.offsets()[3]).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(Dat::parse(&[1, 0]).is_err());
+        assert!(Dat::parse(&[1, 0, 0, 0, 0xAA]).is_err());
+        let mut bytes = 2u32.to_le_bytes().to_vec();
+        bytes.extend([0; 3]);
+        bytes.extend(MAGIC);
+        assert!(Dat::parse(&bytes).is_err());
+    }
+}
@@ -0,0 +1,681 @@
+use anyhow::Context;
+use image::RgbaImage;
+use std::io::Read;
+
+const MAGIC: &[u8; 4] = b"DDS ";
+const HEADER_SIZE: usize = 128;
+const DX10_HEADER_SIZE: usize = 20;
+
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_ALPHA: u32 = 0x2;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_LUMINANCE: u32 = 0x20000;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    Bc1,
+    Bc2,
+    Bc3,
+    Bc4,
+    Bc5,
+    Bc7,
+    /// Uncompressed pixels described by a bit count and per-channel masks.
+    Masked {
+        bits: u32,
+        masks: [u32; 4],
+    },
+}
+
+pub struct Header {
+    pub width: u32,
+    pub height: u32,
+    pub mip_count: u32,
+    pub format: Format,
+    /// Length of the header including the DX10 extension, i.e. where the pixel data starts.
+    size: usize,
+}
+
+/// How a `.dds` file is stored in the bundles.
+pub enum Stored {
+    Dds(Vec<u8>),
+    /// The file is a reference to the texture at another path.
+    Link(String),
+}
+
+/// Unwraps a `.dds` file as stored by the game, which is either a plain DDS file, a `*` followed by
+/// the path of another texture, or the uncompressed size as u32 followed by brotli compressed data.
+pub fn unwrap(bytes: Vec<u8>) -> anyhow::Result<Stored> {
+    if bytes.starts_with(MAGIC) {
+        Ok(Stored::Dds(bytes))
+    } else if let Some(path) = bytes.strip_prefix(b"*") {
+        let path = std::str::from_utf8(path).context("texture link is not UTF-8")?;
+        Ok(Stored::Link(
+            path.trim_end_matches('\0').trim().to_lowercase(),
+        ))
+    } else if bytes.len() > 4 {
+        // the size is only a hint, and a corrupt one should not reserve gigabytes up front
+        let size = (u32_at(&bytes, 0) as usize).min(bytes.len() * 64);
+        let mut out = Vec::with_capacity(size);
+        brotli_decompressor::Decompressor::new(&bytes[4..], 4096)
+            .read_to_end(&mut out)
+            .context("decompressing texture")?;
+        Ok(Stored::Dds(out))
+    } else {
+        anyhow::bail!("not a DDS file")
+    }
+}
+
+/// Reads just the header of a `.dds` file as stored by the game, without decompressing the pixels.
+/// Links to other textures have no header of their own.
+pub fn read_header(bytes: &[u8]) -> anyhow::Result<Option<Header>> {
+    if bytes.starts_with(MAGIC) {
+        Header::parse(bytes).map(Some)
+    } else if bytes.starts_with(b"*") {
+        Ok(None)
+    } else if bytes.len() > 4 {
+        let mut header = Vec::with_capacity(HEADER_SIZE + DX10_HEADER_SIZE);
+        brotli_decompressor::Decompressor::new(&bytes[4..], 4096)
+            .take((HEADER_SIZE + DX10_HEADER_SIZE) as u64)
+            .read_to_end(&mut header)
+            .context("decompressing texture header")?;
+        Header::parse(&header).map(Some)
+    } else {
+        anyhow::bail!("not a DDS file")
+    }
+}
+
+impl Header {
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            anyhow::bail!("not a DDS file");
+        }
+        let height = u32_at(bytes, 12);
+        let width = u32_at(bytes, 16);
+        // a chain ends at 1x1, so any further levels in the header are bogus
+        let max_mips = u32::BITS - width.max(height).max(1).leading_zeros();
+        let mip_count = u32_at(bytes, 28).clamp(1, max_mips);
+        let pf_flags = u32_at(bytes, 80);
+        let four_cc = &bytes[84..88];
+
+        let mut size = HEADER_SIZE;
+        let format = if pf_flags & DDPF_FOURCC != 0 {
+            match four_cc {
+                b"DXT1" => Format::Bc1,
+                b"DXT2" | b"DXT3" => Format::Bc2,
+                b"DXT4" | b"DXT5" => Format::Bc3,
+                b"ATI1" | b"BC4U" => Format::Bc4,
+                b"ATI2" | b"BC5U" => Format::Bc5,
+                b"DX10" => {
+                    size += DX10_HEADER_SIZE;
+                    if bytes.len() < size {
+                        anyhow::bail!("DDS file truncated in DX10 header");
+                    }
+                    dxgi_format(u32_at(bytes, HEADER_SIZE))?
+                }
+                other => anyhow::bail!("unsupported DDS format {}", String::from_utf8_lossy(other)),
+            }
+        } else {
+            let bits = u32_at(bytes, 88);
+            let mut masks = [
+                u32_at(bytes, 92),
+                u32_at(bytes, 96),
+                u32_at(bytes, 100),
+                u32_at(bytes, 104),
+            ];
+            if pf_flags & (DDPF_ALPHAPIXELS | DDPF_ALPHA) == 0 {
+                masks[3] = 0;
+            }
+            if pf_flags & DDPF_LUMINANCE != 0 {
+                masks[1] = masks[0];
+                masks[2] = masks[0];
+            }
+            if !matches!(bits, 8 | 16 | 24 | 32) {
+                anyhow::bail!("unsupported DDS bit count {bits}");
+            }
+            Format::Masked { bits, masks }
+        };
+
+        Ok(Self {
+            width,
+            height,
+            mip_count,
+            format,
+            size,
+        })
+    }
+
+    pub fn dimensions(&self, level: u32) -> (u32, u32) {
+        let shrink = |size: u32| size.checked_shr(level).unwrap_or(0).max(1);
+        (shrink(self.width), shrink(self.height))
+    }
+
+    /// Size in bytes of the given mip level.
+    fn surface_size(&self, level: u32) -> usize {
+        let (width, height) = self.dimensions(level);
+        match self.format {
+            Format::Masked { bits, .. } => width as usize * height as usize * bits as usize / 8,
+            format => {
+                width.div_ceil(4) as usize * height.div_ceil(4) as usize * format.block_size()
+            }
+        }
+    }
+}
+
+/// A parsed DDS file, borrowing the pixel data of one mip level.
+pub struct Dds<'a> {
+    pub header: Header,
+    pub width: u32,
+    pub height: u32,
+    data: &'a [u8],
+}
+
+impl<'a> Dds<'a> {
+    pub fn parse(bytes: &'a [u8]) -> anyhow::Result<Self> {
+        Self::parse_mip(bytes, 0)
+    }
+
+    /// Parses the file, picking the given mip level or the smallest one if there are fewer.
+    pub fn parse_mip(bytes: &'a [u8], level: u32) -> anyhow::Result<Self> {
+        let header = Header::parse(bytes)?;
+        let level = level.min(header.mip_count - 1);
+        let start = header.size + (0..level).map(|l| header.surface_size(l)).sum::<usize>();
+        let data = bytes
+            .get(start..start + header.surface_size(level))
+            .context("DDS file truncated in pixel data")?;
+        let (width, height) = header.dimensions(level);
+        Ok(Self {
+            header,
+            width,
+            height,
+            data,
+        })
+    }
+
+    pub fn decode(&self) -> anyhow::Result<RgbaImage> {
+        self.decode_region(0, 0, self.width, self.height)
+    }
+
+    /// Decodes only the given rectangle, touching no more blocks than needed to cover it.
+    pub fn decode_region(&self, x: u32, y: u32, w: u32, h: u32) -> anyhow::Result<RgbaImage> {
+        let (width, height) = (self.width, self.height);
+        if x.checked_add(w).is_none_or(|r| r > width) || y.checked_add(h).is_none_or(|b| b > height)
+        {
+            anyhow::bail!("region {x},{y} {w}x{h} outside of {width}x{height} texture");
+        }
+        let mut out = RgbaImage::new(w, h);
+        match self.header.format {
+            Format::Masked { bits, masks } => {
+                let bpp = bits as usize / 8;
+                let pitch = width as usize * bpp;
+                for py in 0..h {
+                    for px in 0..w {
+                        let at = (y + py) as usize * pitch + (x + px) as usize * bpp;
+                        let mut raw = [0; 4];
+                        raw[..bpp].copy_from_slice(&self.data[at..at + bpp]);
+                        let value = u32::from_le_bytes(raw);
+                        let mut pixel = [0, 0, 0, 255];
+                        for (c, &mask) in masks.iter().enumerate() {
+                            if mask != 0 {
+                                pixel[c] = scale_masked(value, mask);
+                            }
+                        }
+                        out.put_pixel(px, py, image::Rgba(pixel));
+                    }
+                }
+            }
+            format => {
+                let block_size = format.block_size();
+                let blocks_wide = width.div_ceil(4) as usize;
+                let mut pixels = [[0u8; 4]; 16];
+                for by in y / 4..(y + h).div_ceil(4) {
+                    for bx in x / 4..(x + w).div_ceil(4) {
+                        let at = (by as usize * blocks_wide + bx as usize) * block_size;
+                        let block = &self.data[at..at + block_size];
+                        match format {
+                            Format::Bc1 => decode_bc1(block, &mut pixels, true),
+                            Format::Bc2 => {
+                                decode_bc1(&block[8..], &mut pixels, false);
+                                decode_explicit_alpha(&block[..8], &mut pixels);
+                            }
+                            Format::Bc3 => {
+                                decode_bc1(&block[8..], &mut pixels, false);
+                                decode_alpha(&block[..8], &mut pixels, 3);
+                            }
+                            Format::Bc4 => {
+                                decode_alpha(block, &mut pixels, 0);
+                                for pixel in pixels.iter_mut() {
+                                    *pixel = [pixel[0], pixel[0], pixel[0], 255];
+                                }
+                            }
+                            Format::Bc5 => {
+                                decode_alpha(&block[..8], &mut pixels, 0);
+                                decode_alpha(&block[8..], &mut pixels, 1);
+                                for pixel in pixels.iter_mut() {
+                                    pixel[2] = 0;
+                                    pixel[3] = 255;
+                                }
+                            }
+                            Format::Bc7 => decode_bc7(block, &mut pixels),
+                            Format::Masked { .. } => unreachable!(),
+                        }
+                        for (i, pixel) in pixels.iter().enumerate() {
+                            let (px, py) = (bx * 4 + i as u32 % 4, by * 4 + i as u32 / 4);
+                            if px >= x && px < x + w && py >= y && py < y + h {
+                                out.put_pixel(px - x, py - y, image::Rgba(*pixel));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl Format {
+    /// Lowercase name as stored in the index, e.g. `bc7` or `b8g8r8a8`.
+    pub fn name(&self) -> String {
+        match self {
+            Format::Bc1 => "bc1".to_string(),
+            Format::Bc2 => "bc2".to_string(),
+            Format::Bc3 => "bc3".to_string(),
+            Format::Bc4 => "bc4".to_string(),
+            Format::Bc5 => "bc5".to_string(),
+            Format::Bc7 => "bc7".to_string(),
+            Format::Masked { masks, .. } if masks[0] == masks[1] && masks[1] == masks[2] => {
+                let mut name = String::new();
+                for (c, mask) in [("l", masks[0]), ("a", masks[3])] {
+                    if mask != 0 {
+                        name.push_str(&format!("{c}{}", mask.count_ones()));
+                    }
+                }
+                name
+            }
+            Format::Masked { masks, .. } => {
+                // channels in order from the lowest bit, like DXGI format names
+                let mut channels = ["r", "g", "b", "a"]
+                    .into_iter()
+                    .zip(*masks)
+                    .filter(|&(_, mask)| mask != 0)
+                    .collect::<Vec<_>>();
+                channels.sort_by_key(|&(_, mask)| mask.trailing_zeros());
+                channels
+                    .into_iter()
+                    .map(|(c, mask)| format!("{c}{}", mask.count_ones()))
+                    .collect()
+            }
+        }
+    }
+
+    fn block_size(self) -> usize {
+        match self {
+            Format::Bc1 | Format::Bc4 => 8,
+            Format::Bc2 | Format::Bc3 | Format::Bc5 | Format::Bc7 => 16,
+            Format::Masked { bits, .. } => bits as usize / 8,
+        }
+    }
+}
+
+fn dxgi_format(dxgi: u32) -> anyhow::Result<Format> {
+    const RGBA: [u32; 4] = [0xff, 0xff00, 0xff0000, 0xff000000];
+    const BGRA: [u32; 4] = [0xff0000, 0xff00, 0xff, 0xff000000];
+    const BGRX: [u32; 4] = [0xff0000, 0xff00, 0xff, 0];
+    Ok(match dxgi {
+        28 | 29 => Format::Masked {
+            bits: 32,
+            masks: RGBA,
+        },
+        87 | 91 => Format::Masked {
+            bits: 32,
+            masks: BGRA,
+        },
+        88 | 93 => Format::Masked {
+            bits: 32,
+            masks: BGRX,
+        },
+        61 => Format::Masked {
+            bits: 8,
+            masks: [0xff, 0xff, 0xff, 0],
+        },
+        65 => Format::Masked {
+            bits: 8,
+            masks: [0, 0, 0, 0xff],
+        },
+        70..=72 => Format::Bc1,
+        73..=75 => Format::Bc2,
+        76..=78 => Format::Bc3,
+        79 | 80 => Format::Bc4,
+        82 | 83 => Format::Bc5,
+        97..=99 => Format::Bc7,
+        other => anyhow::bail!("unsupported DXGI format {other}"),
+    })
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn scale_masked(value: u32, mask: u32) -> u8 {
+    let bits = mask.count_ones();
+    let v = (value & mask) >> mask.trailing_zeros();
+    if bits >= 8 {
+        (v >> (bits - 8)) as u8
+    } else {
+        (v * 255 / ((1 << bits) - 1)) as u8
+    }
+}
+
+fn rgb565(c: u16) -> [u8; 4] {
+    let r = (c >> 11) & 0x1f;
+    let g = (c >> 5) & 0x3f;
+    let b = c & 0x1f;
+    [
+        ((r << 3) | (r >> 2)) as u8,
+        ((g << 2) | (g >> 4)) as u8,
+        ((b << 3) | (b >> 2)) as u8,
+        255,
+    ]
+}
+
+fn mix(a: [u8; 4], b: [u8; 4], wa: u32, wb: u32) -> [u8; 4] {
+    let mut out = [0; 4];
+    for c in 0..4 {
+        out[c] = ((a[c] as u32 * wa + b[c] as u32 * wb) / (wa + wb)) as u8;
+    }
+    out
+}
+
+/// Decodes a BC1 colour block. BC2 and BC3 always use the four colour mode.
+fn decode_bc1(block: &[u8], out: &mut [[u8; 4]; 16], allow_transparent: bool) {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (e0, e1) = (rgb565(c0), rgb565(c1));
+    let palette = if c0 > c1 || !allow_transparent {
+        [e0, e1, mix(e0, e1, 2, 1), mix(e0, e1, 1, 2)]
+    } else {
+        [e0, e1, mix(e0, e1, 1, 1), [0, 0, 0, 0]]
+    };
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    for (i, pixel) in out.iter_mut().enumerate() {
+        *pixel = palette[(indices >> (2 * i) & 3) as usize];
+    }
+}
+
+/// Decodes an interpolated 8 byte single channel block (BC3 alpha, BC4, BC5) into channel `c`.
+fn decode_alpha(block: &[u8], out: &mut [[u8; 4]; 16], c: usize) {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let mut palette = [a0, a1, 0, 0, 0, 0, 0, 0];
+    if a0 > a1 {
+        for i in 1..7 {
+            palette[i + 1] = ((7 - i) * a0 as usize + i * a1 as usize) as u32 / 7;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = ((5 - i) * a0 as usize + i * a1 as usize) as u32 / 5;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+    let mut indices = [0; 8];
+    indices[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(indices);
+    for (i, pixel) in out.iter_mut().enumerate() {
+        pixel[c] = palette[(indices >> (3 * i) & 7) as usize] as u8;
+    }
+}
+
+/// Decodes the 4 bit per pixel alpha of a BC2 block.
+fn decode_explicit_alpha(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    for (i, pixel) in out.iter_mut().enumerate() {
+        let a = block[i / 2] >> (4 * (i % 2)) & 0xf;
+        pixel[3] = a << 4 | a;
+    }
+}
+
+struct Bits {
+    value: u128,
+    pos: u32,
+}
+
+impl Bits {
+    fn read(&mut self, count: u32) -> u32 {
+        if count == 0 {
+            return 0;
+        }
+        let v = (self.value >> self.pos) as u32 & ((1u64 << count) - 1) as u32;
+        self.pos += count;
+        v
+    }
+}
+
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_pbits: bool,
+    shared_pbits: bool,
+    index_bits: u32,
+    index_bits2: u32,
+}
+
+#[allow(clippy::too_many_arguments)]
+const fn mode(
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_pbits: bool,
+    shared_pbits: bool,
+    index_bits: u32,
+    index_bits2: u32,
+) -> Bc7Mode {
+    Bc7Mode {
+        subsets,
+        partition_bits,
+        rotation_bits,
+        index_selection_bits,
+        color_bits,
+        alpha_bits,
+        endpoint_pbits,
+        shared_pbits,
+        index_bits,
+        index_bits2,
+    }
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    mode(3, 4, 0, 0, 4, 0, true, false, 3, 0),
+    mode(2, 6, 0, 0, 6, 0, false, true, 3, 0),
+    mode(3, 6, 0, 0, 5, 0, false, false, 2, 0),
+    mode(2, 6, 0, 0, 7, 0, true, false, 2, 0),
+    mode(1, 0, 2, 1, 5, 6, false, false, 2, 3),
+    mode(1, 0, 2, 0, 7, 8, false, false, 2, 2),
+    mode(1, 0, 0, 0, 7, 7, true, false, 4, 0),
+    mode(2, 6, 0, 0, 5, 5, true, false, 2, 0),
+];
+
+/// Subset of each pixel for the 2 subset partitions, one bit per pixel.
+const PARTITIONS2: [u16; 64] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80, 0xC800, 0xFFEC, 0xFE80, 0xE800,
+    0xFFE8, 0xFF00, 0xFFF0, 0xF000, 0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE,
+    0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C, 0xAAAA, 0xF0F0, 0x5A5A, 0x33CC,
+    0x3C3C, 0x55AA, 0x9696, 0xA55A, 0x73CE, 0x13C8, 0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660,
+    0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C, 0x9336, 0x9CC6, 0x817E, 0xE718,
+    0xCCF0, 0x0FCC, 0x7744, 0xEE22,
+];
+
+/// Subset of each pixel for the 3 subset partitions, two bits per pixel.
+const PARTITIONS3: [u32; 64] = [
+    0xAA685050, 0x6A5A5040, 0x5A5A4200, 0x5450A0A8, 0xA5A50000, 0xA0A05050, 0x5555A0A0, 0x5A5A5050,
+    0xAA550000, 0xAA555500, 0xAAAA5500, 0x90909090, 0x94949494, 0xA4A4A4A4, 0xA9A59450, 0x2A0A4250,
+    0xA5945040, 0x0A425054, 0xA5A5A500, 0x55A0A0A0, 0xA8A85454, 0x6A6A4040, 0xA4A45000, 0x1A1A0500,
+    0x0050A4A4, 0xAAA59090, 0x14696914, 0x69691400, 0xA08585A0, 0xAA821414, 0x50A4A450, 0x6A5A0200,
+    0xA9A58000, 0x5090A0A8, 0xA8A09050, 0x24242424, 0x00AA5500, 0x24924924, 0x24499224, 0x50A50A50,
+    0x500AA550, 0xAAAA4444, 0x66660000, 0xA5A0A5A0, 0x50A050A0, 0x69286928, 0x44AAAA44, 0x66666600,
+    0xAA444444, 0x54A854A8, 0x95809580, 0x96969600, 0xA85454A8, 0x80959580, 0xAA141414, 0x96960000,
+    0xAAAA1414, 0xA05050A0, 0xA0A5A5A0, 0x96000000, 0x40804080, 0xA9A8A9A8, 0xAAAAAA44, 0x2A4A5254,
+];
+
+const ANCHOR2: [usize; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 2, 8, 2, 2, 8, 8, 15, 2, 8,
+    2, 2, 8, 8, 2, 2, 15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, 6, 2, 6, 8, 15, 15, 2,
+    2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+const ANCHOR3_2: [usize; 64] = [
+    3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3, 3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5,
+    15, 15, 8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15, 3, 15, 5, 5, 5, 8, 5, 10, 5,
+    10, 8, 13, 15, 12, 3, 3,
+];
+
+const ANCHOR3_3: [usize; 64] = [
+    15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8, 15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6,
+    10, 15, 15, 10, 8, 15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8, 15, 3, 15, 15, 15,
+    15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+];
+
+const WEIGHTS2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+fn weights(bits: u32) -> &'static [u32] {
+    match bits {
+        2 => &WEIGHTS2,
+        3 => &WEIGHTS3,
+        _ => &WEIGHTS4,
+    }
+}
+
+fn decode_bc7(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    let mut raw = [0; 16];
+    raw.copy_from_slice(block);
+    let mut bits = Bits {
+        value: u128::from_le_bytes(raw),
+        pos: 0,
+    };
+    let Some(mode_index) = (0..8).find(|&m| bits.value >> m & 1 == 1) else {
+        // reserved mode, decoders are required to output transparent black
+        *out = [[0; 4]; 16];
+        return;
+    };
+    bits.pos = mode_index + 1;
+    let mode = &BC7_MODES[mode_index as usize];
+
+    let partition = bits.read(mode.partition_bits) as usize;
+    let rotation = bits.read(mode.rotation_bits);
+    let index_selection = bits.read(mode.index_selection_bits);
+
+    let subset_of = |i: usize| -> usize {
+        match mode.subsets {
+            2 => (PARTITIONS2[partition] >> i & 1) as usize,
+            3 => (PARTITIONS3[partition] >> (2 * i) & 3) as usize,
+            _ => 0,
+        }
+    };
+    let is_anchor = |i: usize| -> bool {
+        i == 0
+            || match mode.subsets {
+                2 => i == ANCHOR2[partition],
+                3 => i == ANCHOR3_2[partition] || i == ANCHOR3_3[partition],
+                _ => false,
+            }
+    };
+
+    // endpoints[subset * 2 + endpoint][channel]
+    let mut endpoints = [[0u32; 4]; 6];
+    let count = mode.subsets * 2;
+    for c in 0..3 {
+        for endpoint in endpoints.iter_mut().take(count) {
+            endpoint[c] = bits.read(mode.color_bits);
+        }
+    }
+    if mode.alpha_bits > 0 {
+        for endpoint in endpoints.iter_mut().take(count) {
+            endpoint[3] = bits.read(mode.alpha_bits);
+        }
+    }
+
+    let (mut color_bits, mut alpha_bits) = (mode.color_bits, mode.alpha_bits);
+    if mode.endpoint_pbits || mode.shared_pbits {
+        let mut pbits = [0; 6];
+        if mode.endpoint_pbits {
+            for p in pbits.iter_mut().take(count) {
+                *p = bits.read(1);
+            }
+        } else {
+            for s in 0..mode.subsets {
+                let p = bits.read(1);
+                pbits[s * 2] = p;
+                pbits[s * 2 + 1] = p;
+            }
+        }
+        for (endpoint, p) in endpoints.iter_mut().zip(pbits).take(count) {
+            for (c, v) in endpoint.iter_mut().enumerate() {
+                if c < 3 || mode.alpha_bits > 0 {
+                    *v = (*v << 1) | p;
+                }
+            }
+        }
+        color_bits += 1;
+        if mode.alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(count) {
+        for (c, v) in endpoint.iter_mut().enumerate() {
+            let n = if c < 3 { color_bits } else { alpha_bits };
+            *v = if n == 0 {
+                255
+            } else {
+                (*v << (8 - n)) | (*v >> (2 * n - 8))
+            };
+        }
+    }
+
+    let mut indices = [0u32; 16];
+    for (i, index) in indices.iter_mut().enumerate() {
+        let n = mode.index_bits - is_anchor(i) as u32;
+        *index = bits.read(n);
+    }
+    let mut indices2 = [0u32; 16];
+    if mode.index_bits2 > 0 {
+        for (i, index) in indices2.iter_mut().enumerate() {
+            let n = mode.index_bits2 - (i == 0) as u32;
+            *index = bits.read(n);
+        }
+    }
+
+    for (i, pixel) in out.iter_mut().enumerate() {
+        let s = subset_of(i);
+        let (e0, e1) = (endpoints[s * 2], endpoints[s * 2 + 1]);
+        let (color_weight, alpha_weight) = if mode.index_bits2 == 0 {
+            let w = weights(mode.index_bits)[indices[i] as usize];
+            (w, w)
+        } else if index_selection == 0 {
+            (
+                weights(mode.index_bits)[indices[i] as usize],
+                weights(mode.index_bits2)[indices2[i] as usize],
+            )
+        } else {
+            (
+                weights(mode.index_bits2)[indices2[i] as usize],
+                weights(mode.index_bits)[indices[i] as usize],
+            )
+        };
+        for c in 0..4 {
+            let w = if c < 3 { color_weight } else { alpha_weight };
+            pixel[c] = (((64 - w) * e0[c] + w * e1[c] + 32) >> 6) as u8;
+        }
+        match rotation {
+            1 => pixel.swap(0, 3),
+            2 => pixel.swap(1, 3),
+            3 => pixel.swap(2, 3),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::formats::mesh::Geometry;
+use serde_json::{json, Value};
+
+const MAGIC: &[u8; 4] = b"glTF";
+const VERSION: u32 = 2;
+const JSON_CHUNK: u32 = 0x4e4f534a;
+const BIN_CHUNK: u32 = 0x004e4942;
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Encodes a mesh as binary glTF with a single primitive, textured from `texture` if given.
+pub fn encode(geometry: &Geometry, texture: Option<&str>) -> Vec<u8> {
+    let mut bin = Vec::with_capacity(geometry.positions.len() * 20 + geometry.indices.len() * 4);
+    bin.extend(
+        geometry
+            .positions
+            .iter()
+            .flatten()
+            .flat_map(|v| v.to_le_bytes()),
+    );
+    let uvs_offset = bin.len();
+    bin.extend(geometry.uvs.iter().flatten().flat_map(|v| v.to_le_bytes()));
+    let indices_offset = bin.len();
+    bin.extend(geometry.indices.iter().flat_map(|v| v.to_le_bytes()));
+
+    let vertices = geometry.positions.len();
+    // viewers expect the exact bounds of the positions rather than the looser stored ones
+    let (mut min, mut max) = ([f32::MAX; 3], [f32::MIN; 3]);
+    for position in &geometry.positions {
+        for i in 0..3 {
+            min[i] = min[i].min(position[i]);
+            max[i] = max[i].max(position[i]);
+        }
+    }
+    let mut gltf = json!({
+        "asset": { "version": "2.0", "generator": env!("CARGO_PKG_NAME") },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0 }],
+        "meshes": [{
+            "primitives": [{
+                "attributes": { "POSITION": 0, "TEXCOORD_0": 1 },
+                "indices": 2,
+            }],
+        }],
+        "buffers": [{ "byteLength": bin.len() }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": uvs_offset, "target": ARRAY_BUFFER },
+            {
+                "buffer": 0,
+                "byteOffset": uvs_offset,
+                "byteLength": indices_offset - uvs_offset,
+                "target": ARRAY_BUFFER,
+            },
+            {
+                "buffer": 0,
+                "byteOffset": indices_offset,
+                "byteLength": bin.len() - indices_offset,
+                "target": ELEMENT_ARRAY_BUFFER,
+            },
+        ],
+        "accessors": [
+            {
+                "bufferView": 0,
+                "componentType": FLOAT,
+                "count": vertices,
+                "type": "VEC3",
+                "min": min,
+                "max": max,
+            },
+            { "bufferView": 1, "componentType": FLOAT, "count": vertices, "type": "VEC2" },
+            {
+                "bufferView": 2,
+                "componentType": UNSIGNED_INT,
+                "count": geometry.indices.len(),
+                "type": "SCALAR",
+            },
+        ],
+    });
+    if let Some(texture) = texture {
+        let root = gltf.as_object_mut().unwrap();
+        root.insert("images".into(), json!([{ "uri": texture }]));
+        root.insert("samplers".into(), json!([{}]));
+        root.insert("textures".into(), json!([{ "source": 0, "sampler": 0 }]));
+        root.insert(
+            "materials".into(),
+            json!([{
+                "pbrMetallicRoughness": {
+                    "baseColorTexture": { "index": 0 },
+                    "metallicFactor": 0.0,
+                },
+                "doubleSided": true,
+            }]),
+        );
+        gltf["meshes"][0]["primitives"][0]["material"] = Value::from(0);
+    }
+
+    let mut json = serde_json::to_vec(&gltf).unwrap();
+    pad(&mut json, b' ');
+    pad(&mut bin, 0);
+    let length = 12 + 8 + json.len() + 8 + bin.len();
+    let mut glb = Vec::with_capacity(length);
+    glb.extend(MAGIC);
+    glb.extend(VERSION.to_le_bytes());
+    glb.extend((length as u32).to_le_bytes());
+    for (kind, chunk) in [(JSON_CHUNK, json), (BIN_CHUNK, bin)] {
+        glb.extend((chunk.len() as u32).to_le_bytes());
+        glb.extend(kind.to_le_bytes());
+        glb.extend(chunk);
+    }
+    glb
+}
+
+/// Chunks are aligned to four bytes.
+fn pad(chunk: &mut Vec<u8>, with: u8) {
+    chunk.resize(chunk.len().next_multiple_of(4), with);
+}
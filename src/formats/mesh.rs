@@ -0,0 +1,278 @@
+use crate::formats::reader::Reader;
+
+/// Extensions of the static meshes whose headers are read: fixed meshes, skinned meshes and tile
+/// geometry.
+pub const MESH_EXT: &[&str] = &["fmt", "smd", "tgm"];
+
+/// What the header of a mesh says about it.
+pub struct MeshInfo {
+    /// Tile geometry does not store totals for its sub-meshes in the header.
+    pub vertices: Option<u32>,
+    pub triangles: Option<u32>,
+    /// Minimum x, y, z followed by maximum x, y, z.
+    pub bbox: [f32; 6],
+    /// Material paths in the order the mesh lists them, without repeats.
+    pub materials: Vec<String>,
+}
+
+/// Vertex counts and bounds from the start of a mesh, followed by where the header ends.
+struct Header {
+    vertices: Option<u32>,
+    triangles: Option<u32>,
+    bbox: [f32; 6],
+    end: usize,
+}
+
+impl Header {
+    fn parse(ext: &str, data: &[u8]) -> anyhow::Result<Self> {
+        let r = &mut Reader::new(data);
+        // version
+        r.u8()?;
+        let (vertices, triangles, bbox) = match ext {
+            // triangles, vertices, shape count u16, extra entry count u8, bbox
+            "fmt" => {
+                let triangles = r.u32()?;
+                let vertices = r.u32()?;
+                r.skip(3)?;
+                (Some(vertices), Some(triangles), r.bbox()?)
+            }
+            // triangles, vertices, unknown u8, shape count u16, string table size, bbox
+            "smd" => {
+                let triangles = r.u32()?;
+                let vertices = r.u32()?;
+                r.skip(7)?;
+                (Some(vertices), Some(triangles), r.bbox()?)
+            }
+            "tgm" => (None, None, r.bbox()?),
+            _ => anyhow::bail!("{ext} is not a mesh format"),
+        };
+        // each triangle needs at least three u16 indexes
+        if let Some(triangles) = triangles {
+            if triangles as usize * 6 > data.len() {
+                anyhow::bail!("{triangles} triangles do not fit in {} bytes", data.len());
+            }
+        }
+        Ok(Self {
+            vertices,
+            triangles,
+            bbox,
+            end: r.position(),
+        })
+    }
+}
+
+impl MeshInfo {
+    pub fn parse(ext: &str, data: &[u8]) -> anyhow::Result<Self> {
+        let header = Header::parse(ext, data)?;
+        Ok(Self {
+            vertices: header.vertices,
+            triangles: header.triangles,
+            bbox: header.bbox,
+            materials: material_paths(data),
+        })
+    }
+}
+
+/// Triangles with the vertex attributes a viewer needs.
+pub struct Geometry {
+    pub positions: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+}
+
+impl Geometry {
+    /// Decodes the index and vertex buffers of a fixed or skinned mesh.
+    ///
+    /// The buffers follow the shape and string tables, whose layout changes between versions, so
+    /// they are located by looking for the first offset where every index is in range and every
+    /// position lies within the bounding box. Only offsets within `MAX_TABLES_SIZE` of the header
+    /// are tried, so a mesh that fits nowhere fails quickly.
+    pub fn parse(ext: &str, data: &[u8]) -> anyhow::Result<Self> {
+        let header = Header::parse(ext, data)?;
+        let (Some(vertices), Some(triangles)) = (header.vertices, header.triangles) else {
+            anyhow::bail!("{ext} meshes are made of tiles and have no single vertex buffer");
+        };
+        let (vertices, indices) = (vertices as usize, triangles as usize * 3);
+        let index_size = if vertices > u16::MAX as usize { 4 } else { 2 };
+        // skinned meshes append bone indexes and weights to the vertices of fixed meshes
+        let strides = match ext {
+            "smd" => [SKINNED_VERTEX_SIZE, FIXED_VERTEX_SIZE],
+            _ => [FIXED_VERTEX_SIZE, SKINNED_VERTEX_SIZE],
+        };
+        for stride in strides {
+            let size = indices * index_size + vertices * stride;
+            let Some(last) = data.len().checked_sub(size) else {
+                continue;
+            };
+            let last = last.min(header.end + MAX_TABLES_SIZE);
+            let layout = Layout {
+                data,
+                vertices,
+                indices,
+                index_size,
+                stride,
+                bbox: header.bbox,
+            };
+            if let Some(start) = (header.end..=last).find(|&start| layout.fits(start)) {
+                return Ok(layout.decode(start));
+            }
+        }
+        anyhow::bail!("could not locate the vertex buffer, unsupported mesh layout")
+    }
+}
+
+/// The most bytes of shape and string tables expected between the header and the buffers.
+const MAX_TABLES_SIZE: usize = 1 << 18;
+
+/// Position, packed normal and tangent, and UV as two halfs.
+const FIXED_VERTEX_SIZE: usize = 24;
+const SKINNED_VERTEX_SIZE: usize = 32;
+const UV_OFFSET: usize = 20;
+
+/// A guess at the sizes of the index and vertex buffers.
+struct Layout<'a> {
+    data: &'a [u8],
+    vertices: usize,
+    indices: usize,
+    index_size: usize,
+    stride: usize,
+    bbox: [f32; 6],
+}
+
+impl Layout<'_> {
+    fn index(&self, start: usize, i: usize) -> u32 {
+        let at = start + i * self.index_size;
+        match self.index_size {
+            2 => u16::from_le_bytes([self.data[at], self.data[at + 1]]) as u32,
+            _ => u32::from_le_bytes(self.data[at..at + 4].try_into().unwrap()),
+        }
+    }
+
+    fn vertex(&self, start: usize, i: usize) -> usize {
+        start + self.indices * self.index_size + i * self.stride
+    }
+
+    fn position(&self, at: usize) -> [f32; 3] {
+        let f = |at: usize| f32::from_le_bytes(self.data[at..at + 4].try_into().unwrap());
+        [f(at), f(at + 4), f(at + 8)]
+    }
+
+    fn contains(&self, position: [f32; 3]) -> bool {
+        (0..3).all(|i| {
+            let (min, max) = (self.bbox[i], self.bbox[i + 3]);
+            let slack = (max - min) * 1e-3 + 1e-3;
+            position[i] >= min - slack && position[i] <= max + slack
+        })
+    }
+
+    /// Whether the buffers could start at `start`, checking the cheapest signs first.
+    fn fits(&self, start: usize) -> bool {
+        let in_range = |i| self.index(start, i) < self.vertices as u32;
+        let inside = |i| self.contains(self.position(self.vertex(start, i)));
+        (0..self.indices.min(64)).all(in_range)
+            && (0..self.vertices.min(4)).all(inside)
+            && (0..self.indices).all(in_range)
+            && (0..self.vertices).all(inside)
+    }
+
+    fn decode(&self, start: usize) -> Geometry {
+        let vertices = (0..self.vertices).map(|i| self.vertex(start, i));
+        Geometry {
+            positions: vertices.clone().map(|at| self.position(at)).collect(),
+            uvs: vertices
+                .map(|at| {
+                    let h =
+                        |at: usize| half(u16::from_le_bytes([self.data[at], self.data[at + 1]]));
+                    [h(at + UV_OFFSET), h(at + UV_OFFSET + 2)]
+                })
+                .collect(),
+            indices: (0..self.indices).map(|i| self.index(start, i)).collect(),
+        }
+    }
+}
+
+/// Converts a half-precision float.
+fn half(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = (bits >> 10 & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// Material paths in a mesh's string table, in the order they appear. The table is UTF-16 and may
+/// start at an odd offset, so both alignments are scanned.
+fn material_paths(data: &[u8]) -> Vec<String> {
+    let mut found = Vec::new();
+    for start in 0..2 {
+        let units = data[start.min(data.len())..]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]));
+        let mut token = String::new();
+        // a trailing separator ends the last token
+        for (i, unit) in units.chain([0]).enumerate() {
+            match char::from_u32(unit as u32)
+                .filter(|&c| c.is_ascii_alphanumeric() || "_-./\\".contains(c))
+            {
+                Some(c) => token.push(c),
+                None => {
+                    if token.contains('/') && token.ends_with(".mat") {
+                        let at = start + 2 * (i - token.len());
+                        found.push((at, token.replace('\\', "/").to_lowercase()));
+                    }
+                    token.clear();
+                }
+            }
+        }
+    }
+    found.sort();
+    let mut paths = Vec::new();
+    for (_, path) in found {
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{half, material_paths};
+
+    #[test]
+    fn converts_normal_halfs() {
+        assert_eq!(half(0x3c00), 1.0);
+        assert_eq!(half(0xc000), -2.0);
+        assert_eq!(half(0x3800), 0.5);
+        assert_eq!(half(0x3555), 0.333_251_95);
+        assert_eq!(half(0x7bff), 65504.0);
+    }
+
+    #[test]
+    fn converts_zeros_and_subnormals() {
+        assert_eq!(half(0x0000), 0.0);
+        assert!(half(0x8000).is_sign_negative());
+        assert_eq!(half(0x0001), 2f32.powi(-24));
+        assert_eq!(half(0x03ff), 1023.0 * 2f32.powi(-24));
+    }
+
+    #[test]
+    fn converts_infinities_and_nans() {
+        assert_eq!(half(0x7c00), f32::INFINITY);
+        assert_eq!(half(0xfc00), f32::NEG_INFINITY);
+        assert!(half(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn lists_materials_in_file_order() {
+        let mut data = vec![0xff];
+        for text in ["Art/Z.mat", "\0", "Art/UI\\a.mat", "\0", "art/z.mat", "\0"] {
+            data.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        }
+        assert_eq!(material_paths(&data), ["art/z.mat", "art/ui/a.mat"]);
+    }
+}
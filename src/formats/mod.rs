@@ -0,0 +1,12 @@
+pub mod audio;
+pub mod bank;
+pub mod dat;
+pub mod dds;
+pub mod gltf;
+pub mod mesh;
+pub mod psg;
+pub mod reader;
+pub mod references;
+pub mod stat_descriptions;
+pub mod template;
+pub mod text;
@@ -0,0 +1,99 @@
+use crate::formats::reader::Reader;
+use serde::Serialize;
+
+/// The passive skill tree; the atlas and other trees live in their own graphs.
+pub const PASSIVE_TREE: &str = "metadata/passiveskillgraph.psg";
+
+/// A passive skill graph: groups of nodes placed on orbits around the group's centre.
+#[derive(Serialize)]
+pub struct Graph {
+    pub version: u8,
+    /// Nodes the tree can start from, such as the class starting points.
+    pub roots: Vec<u32>,
+    pub groups: Vec<Group>,
+}
+
+#[derive(Serialize)]
+pub struct Group {
+    pub x: f32,
+    pub y: f32,
+    pub flags: u32,
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Serialize)]
+pub struct Node {
+    /// The `PassiveSkillGraphId` of the node's row in `PassiveSkills`.
+    pub id: u32,
+    /// Which ring around the group's centre the node sits on.
+    pub orbit: u32,
+    /// Position of the node on its orbit.
+    pub orbit_index: u32,
+    pub connections: Vec<Connection>,
+}
+
+#[derive(Serialize)]
+pub struct Connection {
+    pub id: u32,
+    /// How far the drawn edge bends away from a straight line, zero when it follows the orbit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub curvature: Option<i32>,
+}
+
+impl Graph {
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        let r = &mut Reader::new(data);
+        let version = r.u8()?;
+        // graph type
+        r.u8()?;
+        // root ids are stored as u64 but refer to the same u32 graph ids as the nodes
+        let roots = (0..r.u32()?)
+            .map(|_| Ok(r.u64()? as u32))
+            .collect::<anyhow::Result<_>>()?;
+
+        let group_count = r.u32()?;
+        let mut groups = Vec::new();
+        for _ in 0..group_count {
+            let x = r.f32()?;
+            let y = r.f32()?;
+            let flags = r.u32()?;
+            // unknown u32, unknown u8
+            r.skip(5)?;
+            let node_count = r.u32()?;
+            let mut nodes = Vec::new();
+            for _ in 0..node_count {
+                let id = r.u32()?;
+                let orbit = r.u32()?;
+                let orbit_index = r.u32()?;
+                let connection_count = r.u32()?;
+                let mut connections = Vec::new();
+                for _ in 0..connection_count {
+                    let id = r.u32()?;
+                    // older graphs only store the connected node
+                    let curvature = (version >= 3)
+                        .then(|| r.u32().map(|v| v as i32))
+                        .transpose()?;
+                    connections.push(Connection { id, curvature });
+                }
+                nodes.push(Node {
+                    id,
+                    orbit,
+                    orbit_index,
+                    connections,
+                });
+            }
+            groups.push(Group { x, y, flags, nodes });
+        }
+        if r.position() != data.len() {
+            anyhow::bail!(
+                "{} bytes left after {group_count} groups, unsupported graph layout",
+                data.len() - r.position()
+            );
+        }
+        Ok(Self {
+            version,
+            roots,
+            groups,
+        })
+    }
+}
@@ -0,0 +1,60 @@
+use anyhow::Context;
+
+/// Little-endian reads over a binary file with bounds checks.
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// How many bytes have been read.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).context("length overflow")?;
+        let bytes = self.data.get(self.pos..end).with_context(|| {
+            format!("unexpected end of file reading {len} bytes at {}", self.pos)
+        })?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    pub fn skip(&mut self, len: usize) -> anyhow::Result<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    pub fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    pub fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into()?))
+    }
+
+    pub fn f32(&mut self) -> anyhow::Result<f32> {
+        Ok(f32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    /// Reads a mesh bounding box and checks that it is one.
+    pub fn bbox(&mut self) -> anyhow::Result<[f32; 6]> {
+        let mut bbox = [0.0; 6];
+        for v in &mut bbox {
+            *v = self.f32()?;
+        }
+        let valid = bbox.iter().all(|v| v.is_finite()) && (0..3).all(|i| bbox[i] <= bbox[i + 3]);
+        if !valid {
+            anyhow::bail!("invalid bounding box {bbox:?}, unsupported mesh layout");
+        }
+        Ok(bbox)
+    }
+}
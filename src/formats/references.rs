@@ -0,0 +1,43 @@
+use crate::formats::template::{self, TEMPLATE_EXT};
+use std::collections::BTreeSet;
+
+/// Extensions of the text assets that point at other files in the pack.
+pub const REFERENCE_EXT: &[&str] = &[
+    "act", "amd", "ao", "aoc", "arm", "atlas", "cht", "clt", "dct", "ddt", "dlp", "ecf", "env",
+    "epk", "et", "ffx", "fxgraph", "gft", "gt", "it", "itc", "mat", "mtd", "ot", "otc", "pet",
+    "sm", "tgr", "tgt", "tmo", "toy", "trl", "tsi",
+];
+
+/// Extracts the paths a text asset refers to, lowercased like the indexed paths.
+///
+/// Any token with a directory and an extension counts as a path. Templates also refer to the
+/// templates they extend, which are named without their extension.
+pub fn extract(text: &str, ext: &str) -> BTreeSet<String> {
+    let mut paths = text
+        .split(|c: char| !(c.is_alphanumeric() || "_-./\\".contains(c)))
+        .filter_map(normalize)
+        .collect::<BTreeSet<_>>();
+    if TEMPLATE_EXT.contains(&ext) {
+        if let Ok(parsed) = template::parse(text) {
+            paths.extend(
+                template::parents(&parsed)
+                    .into_iter()
+                    .map(|parent| format!("{parent}.{ext}")),
+            );
+        }
+    }
+    paths
+}
+
+fn normalize(token: &str) -> Option<String> {
+    let path = token.replace('\\', "/").to_lowercase();
+    let path = path.trim_start_matches("./").trim_start_matches('/');
+    let (dir, name) = path.rsplit_once('/')?;
+    let (stem, ext) = name.rsplit_once('.')?;
+    let valid = !dir.is_empty()
+        && !dir.contains("//")
+        && !stem.is_empty()
+        && (1..=8).contains(&ext.len())
+        && ext.chars().all(|c| c.is_ascii_alphanumeric());
+    valid.then(|| path.to_string())
+}
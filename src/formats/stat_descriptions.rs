@@ -0,0 +1,301 @@
+use anyhow::Context;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+
+/// Translations outside a `lang` block are English.
+const DEFAULT_LANGUAGE: &str = "English";
+
+/// Whether `path` is a stat description file, e.g. `metadata/statdescriptions/stat_descriptions.txt`.
+pub fn is_stat_description(path: &str) -> bool {
+    path.starts_with("metadata/statdescriptions/")
+        && (path.ends_with(".txt") || path.ends_with(".csd"))
+}
+
+#[derive(Serialize, Default)]
+pub struct StatDescriptions {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub includes: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub no_description: Vec<String>,
+    pub descriptions: Vec<Description>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Description {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub stats: Vec<String>,
+    pub languages: BTreeMap<String, Vec<Translation>>,
+}
+
+/// A format string used when every stat value falls into its range.
+#[derive(Serialize, Clone)]
+pub struct Translation {
+    pub ranges: Vec<Range>,
+    pub text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub handlers: Vec<Handler>,
+}
+
+/// Inclusive bounds on a stat value, `None` meaning unbounded.
+#[derive(Serialize, Clone)]
+pub struct Range {
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub negated: bool,
+}
+
+/// Transforms a stat value before formatting, e.g. `negate 1` or `per_minute_to_per_second 2`.
+#[derive(Serialize, Clone)]
+pub struct Handler {
+    pub name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+}
+
+impl StatDescriptions {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(n, line)| (n + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with("//"))
+            .peekable();
+        let mut out = Self::default();
+        while let Some((n, line)) = lines.next() {
+            let (keyword, rest) = split_keyword(line);
+            match keyword {
+                "include" => out.includes.push(unquote(rest).to_string()),
+                "no_description" => out.no_description.push(rest.to_string()),
+                "description" => {
+                    let id = (!rest.is_empty()).then(|| rest.to_string());
+                    let description = Description::parse(id, &mut lines)
+                        .with_context(|| format!("description at line {n}"))?;
+                    out.descriptions.push(description);
+                }
+                // no_identifiers and similar file-level flags
+                _ => {}
+            }
+        }
+        Ok(out)
+    }
+
+    /// Descriptions that format `stat`.
+    pub fn describing<'a>(&'a self, stat: &'a str) -> impl Iterator<Item = &'a Description> {
+        self.descriptions
+            .iter()
+            .filter(move |d| d.stats.iter().any(|s| s == stat))
+    }
+}
+
+impl Description {
+    fn parse<'a>(
+        id: Option<String>,
+        lines: &mut Peekable<impl Iterator<Item = (usize, &'a str)>>,
+    ) -> anyhow::Result<Self> {
+        let (n, line) = lines.next().context("missing stat ids")?;
+        let mut tokens = line.split_whitespace();
+        let count = parse_count(tokens.next(), n)?;
+        let stats = tokens.map(str::to_string).collect::<Vec<_>>();
+        if stats.len() != count {
+            anyhow::bail!("line {n}: expected {count} stat ids, got {}", stats.len());
+        }
+
+        let mut languages = BTreeMap::new();
+        let mut language = DEFAULT_LANGUAGE.to_string();
+        loop {
+            let (n, line) = lines.next().context("missing translation count")?;
+            let count = parse_count(Some(line), n)?;
+            let mut translations = Vec::with_capacity(count);
+            for _ in 0..count {
+                let (n, line) = lines.next().context("missing translation")?;
+                let translation =
+                    Translation::parse(line, stats.len()).with_context(|| format!("line {n}"))?;
+                translations.push(translation);
+            }
+            languages.insert(language, translations);
+
+            match lines.peek() {
+                Some(&(_, line)) if split_keyword(line).0 == "lang" => {
+                    lines.next();
+                    language = unquote(split_keyword(line).1).to_string();
+                }
+                _ => break,
+            }
+        }
+        Ok(Self {
+            id,
+            stats,
+            languages,
+        })
+    }
+}
+
+impl Translation {
+    fn parse(line: &str, stat_count: usize) -> anyhow::Result<Self> {
+        let mut rest = line;
+        let mut ranges = Vec::with_capacity(stat_count);
+        for _ in 0..stat_count {
+            let (token, after) = split_keyword(rest);
+            ranges.push(Range::parse(token)?);
+            rest = after;
+        }
+        let quoted = rest
+            .strip_prefix('"')
+            .context("expected a quoted format string")?;
+        let (text, rest) = quoted.split_once('"').context("unterminated string")?;
+
+        let mut handlers: Vec<Handler> = Vec::new();
+        for token in rest.split_whitespace() {
+            match handlers.last_mut() {
+                Some(handler) if is_argument(handler, token) => {
+                    handler.args.push(token.to_string())
+                }
+                _ => handlers.push(Handler {
+                    name: token.to_string(),
+                    args: Vec::new(),
+                }),
+            }
+        }
+        Ok(Self {
+            ranges,
+            text: text.to_string(),
+            handlers,
+        })
+    }
+}
+
+/// Handler arguments are stat indexes, except for the reminder text id of `reminderstring`.
+fn is_argument(handler: &Handler, token: &str) -> bool {
+    token.parse::<i64>().is_ok() || (handler.name == "reminderstring" && handler.args.is_empty())
+}
+
+impl Range {
+    /// Parses `#`, `5`, `1|#`, `#|-1` or `!0`.
+    fn parse(token: &str) -> anyhow::Result<Self> {
+        let (negated, token) = match token.strip_prefix('!') {
+            Some(token) => (true, token),
+            None => (false, token),
+        };
+        let bound = |b: &str| match b {
+            "#" => Ok(None),
+            b => b
+                .parse()
+                .map(Some)
+                .with_context(|| format!("invalid range bound {b:?}")),
+        };
+        let (min, max) = match token.split_once('|') {
+            Some((min, max)) => (bound(min)?, bound(max)?),
+            None => {
+                let value = bound(token)?;
+                (value, value)
+            }
+        };
+        Ok(Self { min, max, negated })
+    }
+}
+
+fn split_keyword(line: &str) -> (&str, &str) {
+    match line.split_once(char::is_whitespace) {
+        Some((keyword, rest)) => (keyword, rest.trim_start()),
+        None => (line, ""),
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    s.trim().trim_matches('"')
+}
+
+fn parse_count(token: Option<&str>, line: usize) -> anyhow::Result<usize> {
+    let token = token.unwrap_or_default();
+    token
+        .parse()
+        .with_context(|| format!("line {line}: expected a count, got {token:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r#"
+include "Metadata/StatDescriptions/common.txt"
+no_description dummy_stat
+// a comment
+
+description life_regen
+	2 life_regen_per_minute life_regen_rate
+	2
+		# 0 "Regenerate {0} Life per second" per_minute_to_per_second 1
+		1|# !0 "Regenerate {0}% of Life per second" reminderstring ReminderTextLifeRegen negate 2
+	lang "French"
+	1
+		#|-1 # "Régénère {0} Points de vie par seconde"
+description
+	1 attack_speed
+	1
+		# "{0}% increased Attack Speed"
+"#;
+
+    #[test]
+    fn parses_descriptions() {
+        let parsed = StatDescriptions::parse(FILE).unwrap();
+        assert_eq!(parsed.includes, ["Metadata/StatDescriptions/common.txt"]);
+        assert_eq!(parsed.no_description, ["dummy_stat"]);
+        assert_eq!(parsed.descriptions.len(), 2);
+
+        let regen = &parsed.descriptions[0];
+        assert_eq!(regen.id.as_deref(), Some("life_regen"));
+        assert_eq!(regen.stats, ["life_regen_per_minute", "life_regen_rate"]);
+        assert_eq!(
+            regen.languages.keys().collect::<Vec<_>>(),
+            ["English", "French"]
+        );
+
+        let english = &regen.languages["English"];
+        assert_eq!(english.len(), 2);
+        assert_eq!(english[0].text, "Regenerate {0} Life per second");
+        let range = &english[0].ranges[1];
+        assert_eq!(
+            (range.min, range.max, range.negated),
+            (Some(0), Some(0), false)
+        );
+        let handlers = &english[0].handlers;
+        assert_eq!(handlers.len(), 1);
+        assert_eq!(handlers[0].name, "per_minute_to_per_second");
+        assert_eq!(handlers[0].args, ["1"]);
+
+        let ranges = &english[1].ranges;
+        assert_eq!((ranges[0].min, ranges[0].max), (Some(1), None));
+        assert_eq!((ranges[1].min, ranges[1].negated), (Some(0), true));
+        let handlers = &english[1].handlers;
+        assert_eq!(handlers[0].name, "reminderstring");
+        assert_eq!(handlers[0].args, ["ReminderTextLifeRegen"]);
+        assert_eq!(handlers[1].name, "negate");
+        assert_eq!(handlers[1].args, ["2"]);
+
+        let french = &regen.languages["French"][0];
+        assert_eq!(
+            (french.ranges[0].min, french.ranges[0].max),
+            (None, Some(-1))
+        );
+
+        let speed = &parsed.descriptions[1];
+        assert_eq!(speed.id, None);
+        assert_eq!(speed.stats, ["attack_speed"]);
+        assert_eq!(parsed.describing("attack_speed").count(), 1);
+    }
+
+    #[test]
+    fn rejects_malformed_descriptions() {
+        let wrong_count = "description\n\t2 only_one\n\t1\n\t\t# \"{0}\"";
+        assert!(StatDescriptions::parse(wrong_count).is_err());
+        let unquoted = "description\n\t1 stat\n\t1\n\t\t# {0}";
+        assert!(StatDescriptions::parse(unquoted).is_err());
+        let bad_range = "description\n\t1 stat\n\t1\n\t\tx \"{0}\"";
+        assert!(StatDescriptions::parse(bad_range).is_err());
+        let truncated = "description\n\t1 stat\n\t2\n\t\t# \"{0}\"";
+        assert!(StatDescriptions::parse(truncated).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::Context;
+use serde_json::{Map, Value};
+use std::iter::Peekable;
+
+/// Extensions of object templates, which `extends` other templates of the same extension.
+pub const TEMPLATE_EXT: &[&str] = &["ot", "otc", "it", "itc"];
+
+/// Top-level keywords followed by a value without an `=`.
+const KEYWORDS: &[&str] = &["version", "extends"];
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Word(&'a str),
+    Str(&'a str),
+    Equals,
+    Open,
+    Close,
+}
+
+/// Parses an object template into a JSON object of its blocks.
+///
+/// Entries become strings, keys repeated within a block become arrays, flags such as `abstract`
+/// become `true` and `extends` is always an array.
+pub fn parse(text: &str) -> anyhow::Result<Map<String, Value>> {
+    let mut tokens = tokenize(text)?.into_iter().peekable();
+    let mut root = parse_block(&mut tokens, true)?;
+    if let Some(token) = tokens.next() {
+        anyhow::bail!("unexpected {token:?} at top level");
+    }
+    if let Some(extends) = root.remove("extends") {
+        let extends = match extends {
+            Value::Array(values) => values,
+            value => vec![value],
+        };
+        let extends = extends
+            .into_iter()
+            .filter(|v| v.as_str() != Some("nothing"))
+            .collect();
+        root.insert("extends".to_string(), Value::Array(extends));
+    }
+    Ok(root)
+}
+
+/// The paths, without extension, of the templates `template` extends.
+pub fn parents(template: &Map<String, Value>) -> Vec<String> {
+    template
+        .get("extends")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Merges `child` over `base`: blocks merge entry by entry, and child entries replace inherited
+/// ones.
+pub fn merge(base: &mut Map<String, Value>, child: Map<String, Value>) {
+    for (key, value) in child {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Object(base)), Value::Object(child)) => merge(base, child),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn parse_block<'a>(
+    tokens: &mut Peekable<impl Iterator<Item = Token<'a>>>,
+    top_level: bool,
+) -> anyhow::Result<Map<String, Value>> {
+    let mut block = Map::new();
+    while let Some(token) = tokens.next_if(|t| *t != Token::Close) {
+        let key = match token {
+            Token::Word(key) | Token::Str(key) => key,
+            other => anyhow::bail!("expected a key, got {other:?}"),
+        };
+        let value = match tokens.peek() {
+            Some(Token::Equals) => {
+                tokens.next();
+                match tokens.next() {
+                    Some(Token::Word(value) | Token::Str(value)) => Value::from(value),
+                    other => anyhow::bail!("expected a value for {key}, got {other:?}"),
+                }
+            }
+            Some(Token::Open) => {
+                tokens.next();
+                let nested = parse_block(tokens, false)?;
+                if tokens.next() != Some(Token::Close) {
+                    anyhow::bail!("unclosed block {key}");
+                }
+                Value::Object(nested)
+            }
+            Some(Token::Word(value) | Token::Str(value))
+                if top_level && KEYWORDS.contains(&key) =>
+            {
+                let value = Value::from(*value);
+                tokens.next();
+                value
+            }
+            _ => Value::Bool(true),
+        };
+        insert(&mut block, key, value);
+    }
+    Ok(block)
+}
+
+/// Inserts an entry, turning repeated keys into arrays.
+fn insert(block: &mut Map<String, Value>, key: &str, value: Value) {
+    match block.get_mut(key) {
+        Some(Value::Array(values)) if !value.is_object() => values.push(value),
+        Some(existing) if !value.is_object() && !existing.is_object() => {
+            let first = existing.take();
+            *existing = Value::Array(vec![first, value]);
+        }
+        Some(Value::Object(existing)) => {
+            if let Value::Object(value) = value {
+                merge(existing, value);
+            }
+        }
+        _ => {
+            block.insert(key.to_string(), value);
+        }
+    }
+}
+
+fn tokenize(text: &str) -> anyhow::Result<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start();
+        let Some(c) = rest.chars().next() else {
+            return Ok(tokens);
+        };
+        let len = match c {
+            '/' if rest.starts_with("//") => {
+                rest = rest.find('\n').map_or("", |end| &rest[end..]);
+                continue;
+            }
+            '=' => {
+                tokens.push(Token::Equals);
+                1
+            }
+            '{' => {
+                tokens.push(Token::Open);
+                1
+            }
+            '}' => {
+                tokens.push(Token::Close);
+                1
+            }
+            '"' => {
+                // strings hold scripts that span lines and contain braces
+                let end = rest[1..].find('"').context("unterminated string")?;
+                tokens.push(Token::Str(&rest[1..end + 1]));
+                end + 2
+            }
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || "={}\"".contains(c))
+                    .unwrap_or(rest.len());
+                tokens.push(Token::Word(&rest[..end]));
+                end
+            }
+        };
+        rest = &rest[len..];
+    }
+}
@@ -0,0 +1,63 @@
+/// Extensions of the files the game stores as text.
+pub const TEXT_EXT: &[&str] = &[
+    "act", "amd", "ao", "aoc", "arm", "atl", "atlas", "cht", "clt", "csd", "dct", "ddt", "dgr",
+    "dlp", "ecf", "env", "epk", "et", "ffx", "fgp", "filter", "fxgraph", "gft", "gt", "h",
+    "hideout", "hlsl", "inc", "it", "itc", "mat", "mtd", "ot", "otc", "pet", "rs", "slg", "sm",
+    "tgr", "tgt", "tmo", "toy", "trl", "tsi", "tst", "txt", "ui", "xml",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    /// Detects the encoding of a game text file from its BOM, returning the bytes after it.
+    pub fn detect(bytes: &[u8]) -> (Self, &[u8]) {
+        if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+            (Self::Utf8, rest)
+        } else if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+            (Self::Utf16Le, rest)
+        } else if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+            (Self::Utf16Be, rest)
+        } else if looks_like_utf16le(bytes) {
+            (Self::Utf16Le, bytes)
+        } else {
+            (Self::Utf8, bytes)
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16Le => "utf-16le",
+            Self::Utf16Be => "utf-16be",
+        }
+    }
+}
+
+/// Decodes a game text file, which is usually UTF-16LE with a BOM but may also be UTF-8.
+pub fn decode(bytes: &[u8]) -> String {
+    let (encoding, rest) = Encoding::detect(bytes);
+    match encoding {
+        Encoding::Utf8 => String::from_utf8_lossy(rest).into_owned(),
+        Encoding::Utf16Le => utf16(rest, u16::from_le_bytes),
+        Encoding::Utf16Be => utf16(rest, u16::from_be_bytes),
+    }
+}
+
+fn utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|c| from_bytes([c[0], c[1]]))
+        .collect::<Vec<_>>();
+    String::from_utf16_lossy(&units)
+}
+
+/// BOM-less UTF-16LE text is mostly ASCII, so every other byte is zero.
+fn looks_like_utf16le(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(256) & !1];
+    !sample.is_empty() && sample.iter().skip(1).step_by(2).all(|&b| b == 0)
+}
@@ -0,0 +1,462 @@
+use crate::formats::audio::{AudioInfo, AUDIO_EXT};
+use crate::formats::bank;
+use crate::formats::dds;
+use crate::formats::mesh::{MeshInfo, MESH_EXT};
+use crate::formats::references::{self, REFERENCE_EXT};
+use crate::formats::stat_descriptions::{is_stat_description, StatDescriptions};
+use crate::formats::text::{self, TEXT_EXT};
+use crate::index::config::IndexConfig;
+use crate::index::dat_strings::DatStrings;
+use crate::index::report::VersionReport;
+use crate::index::state::{EntryType, Fields};
+use crate::index::virtual_entries::VirtualEntries;
+use csv::ReaderBuilder;
+use std::collections::{HashMap, HashSet};
+use tantivy::TantivyDocument;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Text files beyond this size are generated data rather than something worth searching.
+const MAX_TEXT_SIZE: usize = 16_000_000;
+
+/// Every analyzer, in the order they run on a file.
+const ANALYZERS: &[&dyn Analyzer] = &[
+    &Hashes,
+    &Textures,
+    &Meshes,
+    &Audio,
+    &Sprites,
+    &Banks,
+    &Stats,
+    &References,
+    &DatStringsAnalyzer,
+    &Text,
+];
+
+/// The environment variable that switches on each analyzer for every storage.
+pub const ANALYZER_VARS: &[(&str, &str)] = &[
+    ("INDEX_CONTENT_HASHES", "hashes"),
+    ("INDEX_TEXTURES", "textures"),
+    ("INDEX_MESHES", "meshes"),
+    ("INDEX_AUDIO", "audio"),
+    ("PROCESS_SPRITE_SHEETS", "sprites"),
+    ("INDEX_SOUND_BANKS", "banks"),
+    ("INDEX_STAT_DESCRIPTIONS", "stats"),
+    ("INDEX_REFERENCES", "references"),
+    ("INDEX_DAT_STRINGS", "dat_strings"),
+    ("INDEX_TEXT_CONTENTS", "text"),
+];
+
+/// Whether an analyzer is called `name`.
+pub fn is_analyzer(name: &str) -> bool {
+    ANALYZERS.iter().any(|analyzer| analyzer.name() == name)
+}
+
+/// Extracts something from the contents of a file while its version is indexed.
+pub trait Analyzer: Sync {
+    /// Name used to switch the analyzer on and off and in the build report.
+    fn name(&self) -> &'static str;
+
+    /// Extensions of the files the analyzer reads, or none to read every file.
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// Narrows the files with one of the extensions down to the ones the analyzer reads.
+    fn accepts(&self, _path: &str) -> bool {
+        true
+    }
+
+    /// Adds whatever it finds in `data` to `doc`, the document of the file.
+    fn analyze(
+        &self,
+        cx: &mut AnalyzerContext<'_, '_>,
+        doc: &mut TantivyDocument,
+        data: &[u8],
+    ) -> anyhow::Result<()>;
+}
+
+/// The file being analyzed and what an analyzer may write to besides its document.
+pub struct AnalyzerContext<'a, 'b> {
+    pub fields: &'a Fields,
+    pub path: &'a str,
+    pub ext: &'a str,
+    pub version: &'a str,
+    pub dat_strings: Option<&'a DatStrings>,
+    pub entries: &'a mut VirtualEntries<'b>,
+    pub report: &'a mut VersionReport,
+}
+
+/// The analyzers enabled for a storage, by extension.
+pub struct Analyzers {
+    by_ext: HashMap<&'static str, Vec<&'static dyn Analyzer>>,
+    any_ext: Vec<&'static dyn Analyzer>,
+}
+
+impl Analyzers {
+    pub fn new(config: &IndexConfig, storage: &str) -> Self {
+        let mut by_ext = HashMap::<_, Vec<_>>::new();
+        let mut any_ext = Vec::new();
+        for &analyzer in ANALYZERS {
+            if !config.enabled(analyzer.name(), storage) {
+                continue;
+            }
+            if analyzer.extensions().is_empty() {
+                any_ext.push(analyzer);
+            }
+            for &ext in analyzer.extensions() {
+                by_ext.entry(ext).or_default().push(analyzer);
+            }
+        }
+        Self { by_ext, any_ext }
+    }
+
+    /// The analyzers that read the file at `path`.
+    pub fn for_file<'a>(
+        &'a self,
+        path: &'a str,
+        ext: Option<&str>,
+    ) -> impl Iterator<Item = &'static dyn Analyzer> + 'a {
+        let by_ext = ext.and_then(|ext| self.by_ext.get(ext)).into_iter();
+        self.any_ext
+            .iter()
+            .chain(by_ext.flatten())
+            .copied()
+            .filter(move |analyzer| analyzer.accepts(path))
+    }
+}
+
+/// Whether `path` lists sprites cut from sprite sheets, e.g. `art/uiimages1.txt`.
+fn is_sprite_list(path: &str) -> bool {
+    path.starts_with("art") && path.ends_with(".txt")
+}
+
+/// Indexes a hash of the contents of every file.
+struct Hashes;
+
+impl Analyzer for Hashes {
+    fn name(&self) -> &'static str {
+        "hashes"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn analyze(
+        &self,
+        cx: &mut AnalyzerContext<'_, '_>,
+        doc: &mut TantivyDocument,
+        data: &[u8],
+    ) -> anyhow::Result<()> {
+        doc.add_u64(cx.fields.content_hash, xxh3_64(data));
+        Ok(())
+    }
+}
+
+/// Indexes the dimensions and format of `.dds` files.
+struct Textures;
+
+impl Analyzer for Textures {
+    fn name(&self) -> &'static str {
+        "textures"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["dds"]
+    }
+
+    fn analyze(
+        &self,
+        cx: &mut AnalyzerContext<'_, '_>,
+        doc: &mut TantivyDocument,
+        data: &[u8],
+    ) -> anyhow::Result<()> {
+        if let Some(header) = dds::read_header(data)? {
+            doc.add_u64(cx.fields.texture_width, header.width as u64);
+            doc.add_u64(cx.fields.texture_height, header.height as u64);
+            doc.add_u64(cx.fields.texture_mips, header.mip_count as u64);
+            doc.add_text(cx.fields.texture_format, header.format.name());
+        }
+        Ok(())
+    }
+}
+
+/// Indexes the size, bounds and materials of meshes.
+struct Meshes;
+
+impl Analyzer for Meshes {
+    fn name(&self) -> &'static str {
+        "meshes"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        MESH_EXT
+    }
+
+    fn analyze(
+        &self,
+        cx: &mut AnalyzerContext<'_, '_>,
+        doc: &mut TantivyDocument,
+        data: &[u8],
+    ) -> anyhow::Result<()> {
+        let mesh = MeshInfo::parse(cx.ext, data)?;
+        if let Some(vertices) = mesh.vertices {
+            doc.add_u64(cx.fields.mesh_vertices, vertices as u64);
+        }
+        if let Some(triangles) = mesh.triangles {
+            doc.add_u64(cx.fields.mesh_triangles, triangles as u64);
+        }
+        for v in mesh.bbox {
+            doc.add_f64(cx.fields.mesh_bbox, v as f64);
+        }
+        for material in mesh.materials {
+            doc.add_text(cx.fields.mesh_materials, material);
+        }
+        Ok(())
+    }
+}
+
+/// Indexes the duration and format of audio files.
+struct Audio;
+
+impl Analyzer for Audio {
+    fn name(&self) -> &'static str {
+        "audio"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        AUDIO_EXT
+    }
+
+    fn analyze(
+        &self,
+        cx: &mut AnalyzerContext<'_, '_>,
+        doc: &mut TantivyDocument,
+        data: &[u8],
+    ) -> anyhow::Result<()> {
+        let audio = AudioInfo::parse(cx.ext, data)?;
+        doc.add_f64(cx.fields.audio_duration, audio.duration);
+        doc.add_u64(cx.fields.audio_channels, audio.channels as u64);
+        doc.add_u64(cx.fields.audio_sample_rate, audio.sample_rate as u64);
+        doc.add_text(cx.fields.audio_format, audio.format);
+        Ok(())
+    }
+}
+
+/// Indexes the sprites a sprite list defines at their own paths.
+struct Sprites;
+
+impl Analyzer for Sprites {
+    fn name(&self) -> &'static str {
+        "sprites"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["txt"]
+    }
+
+    fn accepts(&self, path: &str) -> bool {
+        is_sprite_list(path)
+    }
+
+    fn analyze(
+        &self,
+        cx: &mut AnalyzerContext<'_, '_>,
+        _doc: &mut TantivyDocument,
+        data: &[u8],
+    ) -> anyhow::Result<()> {
+        let text = text::decode(data);
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(b' ')
+            .from_reader(text.as_bytes());
+
+        for record in reader.deserialize::<(String, String, u64, u64, u64, u64)>() {
+            let (mut filename, mut source, x, y, x2, y2) = match record {
+                Err(e) => {
+                    let error = format!("Error parsing record from {}: {e}", cx.path);
+                    eprintln!("{error}");
+                    cx.report.sprite_errors.push(error);
+                    continue;
+                }
+                Ok(r) => r,
+            };
+
+            filename = filename.to_lowercase();
+            source = source.to_lowercase();
+
+            let fields = cx.fields;
+            let mut doc = TantivyDocument::new();
+            doc.add_text(fields.sprite_sheet, source);
+            doc.add_text(fields.sprite_txt, cx.path);
+            // min and abs_diff not really necessary as x1 and y1 should always be top left, but what's the harm
+            doc.add_u64(fields.sprite_x, x.min(x2));
+            doc.add_u64(fields.sprite_y, y.min(y2));
+            doc.add_u64(fields.sprite_w, x.abs_diff(x2) + 1);
+            doc.add_u64(fields.sprite_h, y.abs_diff(y2) + 1);
+
+            // sprites named without a directory are listed in art/sprites
+            let (dir, name) = filename
+                .rsplit_once('/')
+                .unwrap_or(("art/sprites", &filename));
+            cx.entries
+                .add(dir, name, &filename, EntryType::SPRITE, doc)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Indexes the sounds of FMOD banks beneath the bank.
+struct Banks;
+
+impl Analyzer for Banks {
+    fn name(&self) -> &'static str {
+        "banks"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["bank"]
+    }
+
+    fn analyze(
+        &self,
+        cx: &mut AnalyzerContext<'_, '_>,
+        _doc: &mut TantivyDocument,
+        data: &[u8],
+    ) -> anyhow::Result<()> {
+        let fields = cx.fields;
+        for sound in bank::sounds(data)? {
+            let mut doc = TantivyDocument::new();
+            doc.add_u64(fields.size, sound.size);
+            doc.add_f64(fields.audio_duration, sound.duration());
+            doc.add_u64(fields.audio_channels, sound.channels as u64);
+            doc.add_u64(fields.audio_sample_rate, sound.sample_rate as u64);
+            doc.add_text(fields.audio_format, sound.format);
+            cx.entries
+                .add_child(cx.path, &sound.name, EntryType::SOUND, doc)?;
+        }
+        Ok(())
+    }
+}
+
+/// Indexes the stat ids a stat description file describes.
+struct Stats;
+
+impl Analyzer for Stats {
+    fn name(&self) -> &'static str {
+        "stats"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["txt", "csd"]
+    }
+
+    fn accepts(&self, path: &str) -> bool {
+        is_stat_description(path)
+    }
+
+    fn analyze(
+        &self,
+        cx: &mut AnalyzerContext<'_, '_>,
+        doc: &mut TantivyDocument,
+        data: &[u8],
+    ) -> anyhow::Result<()> {
+        let descriptions = StatDescriptions::parse(&text::decode(data))?;
+        let stats = descriptions
+            .descriptions
+            .iter()
+            .flat_map(|d| &d.stats)
+            .collect::<HashSet<_>>();
+        for stat in stats {
+            doc.add_text(cx.fields.stat_id, stat);
+        }
+        Ok(())
+    }
+}
+
+/// Indexes the paths text assets refer to.
+struct References;
+
+impl Analyzer for References {
+    fn name(&self) -> &'static str {
+        "references"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        REFERENCE_EXT
+    }
+
+    fn analyze(
+        &self,
+        cx: &mut AnalyzerContext<'_, '_>,
+        doc: &mut TantivyDocument,
+        data: &[u8],
+    ) -> anyhow::Result<()> {
+        for reference in references::extract(&text::decode(data), cx.ext) {
+            doc.add_text(cx.fields.references, reference);
+        }
+        Ok(())
+    }
+}
+
+/// Writes the string columns of dat tables into the dat strings index.
+struct DatStringsAnalyzer;
+
+impl Analyzer for DatStringsAnalyzer {
+    fn name(&self) -> &'static str {
+        "dat_strings"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["datc64"]
+    }
+
+    fn analyze(
+        &self,
+        cx: &mut AnalyzerContext<'_, '_>,
+        _doc: &mut TantivyDocument,
+        data: &[u8],
+    ) -> anyhow::Result<()> {
+        if let Some(dat_strings) = cx.dat_strings {
+            dat_strings.add(&cx.report.storage, cx.version, cx.path, data)?;
+        }
+        Ok(())
+    }
+}
+
+/// Indexes the contents of text files for `content:` searches.
+struct Text;
+
+impl Analyzer for Text {
+    fn name(&self) -> &'static str {
+        "text"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        TEXT_EXT
+    }
+
+    fn analyze(
+        &self,
+        cx: &mut AnalyzerContext<'_, '_>,
+        doc: &mut TantivyDocument,
+        data: &[u8],
+    ) -> anyhow::Result<()> {
+        if data.len() > MAX_TEXT_SIZE {
+            anyhow::bail!("{} bytes is too large to index as text", data.len());
+        }
+        doc.add_text(cx.fields.content, text::decode(data));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_analyzer_has_a_variable() {
+        let names = ANALYZER_VARS.iter().map(|&(_, name)| name);
+        assert!(names.clone().all(is_analyzer));
+        assert_eq!(names.collect::<HashSet<_>>().len(), ANALYZERS.len());
+    }
+}
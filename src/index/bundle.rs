@@ -0,0 +1,260 @@
+use crate::index::state::Fields;
+use anyhow::Context;
+use axum::body::Bytes;
+use lru::LruCache;
+use std::io::Cursor;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use tantivy::schema::Value;
+use tantivy::TantivyDocument;
+use tokio::sync::OnceCell;
+use url::Url;
+
+/// A downloaded bundle whose blocks are only decompressed when a file inside them is read.
+pub struct Bundle {
+    data: Bytes,
+    uncompressed_size: usize,
+    granularity: usize,
+    blocks: Vec<Range<usize>>,
+    decoded: Vec<Option<Vec<u8>>>,
+    ooz: oozextract::Extractor,
+}
+
+impl Bundle {
+    pub fn parse(data: Bytes) -> anyhow::Result<Self> {
+        let cur = &mut Cursor::new(data.as_ref());
+        // uncompressed size u32, payload size u32, header size u32, first file u32, unknown u32
+        cur.set_position(20);
+        let uncompressed_size = read_u64(cur)?;
+        // payload size
+        read_u64(cur)?;
+        let block_count = read_u32(cur)? as usize;
+        let granularity = read_u32(cur)? as usize;
+        // unknown [u32; 4]
+        cur.set_position(cur.position() + 16);
+        let mut start = cur.position() as usize + 4 * block_count;
+        let mut blocks = Vec::with_capacity(block_count);
+        for _ in 0..block_count {
+            let end = start + read_u32(cur)? as usize;
+            blocks.push(start..end);
+            start = end;
+        }
+        if start > data.len() {
+            anyhow::bail!(
+                "bundle truncated: expected {start} bytes, got {}",
+                data.len()
+            );
+        }
+        if granularity == 0 || block_count * granularity < uncompressed_size {
+            anyhow::bail!("bundle blocks do not cover {uncompressed_size} bytes");
+        }
+
+        Ok(Self {
+            data,
+            uncompressed_size,
+            granularity,
+            decoded: vec![None; block_count],
+            blocks,
+            ooz: oozextract::Extractor::new(),
+        })
+    }
+
+    /// Upper bound on the memory the bundle takes once every block is decompressed.
+    fn weight(&self) -> usize {
+        self.data.len() + self.uncompressed_size
+    }
+
+    /// Copies `size` bytes starting at `offset` out of the uncompressed bundle.
+    pub fn read(&mut self, offset: usize, size: usize) -> anyhow::Result<Vec<u8>> {
+        let end = offset + size;
+        if end > self.uncompressed_size {
+            anyhow::bail!(
+                "range {offset}..{end} out of bounds for bundle of {} bytes",
+                self.uncompressed_size
+            );
+        }
+        let mut out = Vec::with_capacity(size);
+        if size == 0 {
+            return Ok(out);
+        }
+        for i in offset / self.granularity..=(end - 1) / self.granularity {
+            let block_start = i * self.granularity;
+            let block = self.block(i)?;
+            let from = offset.max(block_start) - block_start;
+            let to = end.min(block_start + block.len()) - block_start;
+            out.extend_from_slice(&block[from..to]);
+        }
+        Ok(out)
+    }
+
+    fn block(&mut self, i: usize) -> anyhow::Result<&[u8]> {
+        if self.decoded[i].is_none() {
+            let start = i * self.granularity;
+            let len = self.uncompressed_size.min(start + self.granularity) - start;
+            let mut buf = vec![0; len];
+            self.ooz
+                .read_from_slice(&self.data[self.blocks[i].clone()], &mut buf)
+                .with_context(|| format!("decompressing block {i}"))?;
+            self.decoded[i] = Some(buf);
+        }
+        Ok(self.decoded[i].as_deref().unwrap_or_default())
+    }
+}
+
+/// Reads files out of the bundles of one version, keeping the most recently used bundle around so
+/// files sorted by bundle only download each bundle once.
+pub struct Bundles {
+    base: Url,
+    current: Option<(String, Bundle)>,
+}
+
+impl Bundles {
+    pub fn new(version: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            base: Url::parse(version)?.join("Bundles2/")?,
+            current: None,
+        })
+    }
+
+    pub async fn read(&mut self, bundle: &str, offset: u64, size: u64) -> anyhow::Result<Vec<u8>> {
+        let bundle = match &mut self.current {
+            Some((name, b)) if name == bundle => b,
+            current => {
+                let parsed = download(&self.base, bundle).await?;
+                &mut current.insert((bundle.to_string(), parsed)).1
+            }
+        };
+        bundle.read(offset as usize, size as usize)
+    }
+
+    /// Reads the file described by an indexed file document.
+    pub async fn read_doc(
+        &mut self,
+        doc: &TantivyDocument,
+        fields: &Fields,
+    ) -> anyhow::Result<Vec<u8>> {
+        let (bundle, offset, size) = location(doc, fields)?;
+        self.read(bundle, offset, size).await
+    }
+}
+
+/// Bundles shared between requests, evicting the least recently used ones once their combined
+/// weight exceeds the budget.
+pub struct BundleCache {
+    budget: usize,
+    cached: Mutex<Cached>,
+}
+
+/// A cached bundle, locked by whichever request is decompressing from it.
+type SharedBundle = Arc<Mutex<Bundle>>;
+/// Filled by the first request to download a bundle, which concurrent requests for it wait on.
+type Slot = Arc<OnceCell<SharedBundle>>;
+type Key = (String, String);
+
+struct Cached {
+    /// Bundles by version and name, with their weights once downloaded.
+    bundles: LruCache<Key, (Slot, usize)>,
+    weight: usize,
+}
+
+impl BundleCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            cached: Mutex::new(Cached {
+                bundles: LruCache::unbounded(),
+                weight: 0,
+            }),
+        }
+    }
+
+    /// Reads the file described by an indexed file document from a bundle of `version`.
+    pub async fn read_doc(
+        &self,
+        version: &str,
+        doc: &TantivyDocument,
+        fields: &Fields,
+    ) -> anyhow::Result<Vec<u8>> {
+        let (name, offset, size) = location(doc, fields)?;
+        let key = (version.to_string(), name.to_string());
+        let slot = self
+            .cached
+            .lock()
+            .unwrap()
+            .bundles
+            .get_or_insert(key.clone(), || (Slot::default(), 0))
+            .0
+            .clone();
+        let bundle = slot
+            .get_or_try_init(|| async {
+                let base = Url::parse(version)?.join("Bundles2/")?;
+                let bundle = download(&base, name).await?;
+                self.weigh(&key, &slot, bundle.weight());
+                anyhow::Ok(Arc::new(Mutex::new(bundle)))
+            })
+            .await?
+            .clone();
+        // decompressing blocks can take a while, and other readers of the bundle wait on the lock
+        tokio::task::spawn_blocking(move || {
+            bundle.lock().unwrap().read(offset as usize, size as usize)
+        })
+        .await?
+    }
+
+    /// Records the weight of a downloaded bundle and evicts bundles until the cache fits again.
+    fn weigh(&self, key: &Key, slot: &Slot, weight: usize) {
+        let cached = &mut *self.cached.lock().unwrap();
+        match cached.bundles.peek_mut(key) {
+            // the bundle may have been evicted or replaced during the download
+            Some((current, _)) if !Arc::ptr_eq(current, slot) => return,
+            None => return,
+            Some(_) if weight > self.budget => {
+                cached.bundles.pop(key);
+                return;
+            }
+            Some((_, entry)) => *entry = weight,
+        }
+        cached.weight += weight;
+        while cached.weight > self.budget {
+            let Some((_, (_, evicted))) = cached.bundles.pop_lru() else {
+                break;
+            };
+            cached.weight -= evicted;
+        }
+    }
+}
+
+async fn download(base: &Url, bundle: &str) -> anyhow::Result<Bundle> {
+    let url = base.join(&format!("{bundle}.bundle.bin"))?;
+    let response = reqwest::get(url).await?.error_for_status()?;
+    Bundle::parse(response.bytes().await?).with_context(|| format!("parsing bundle {bundle}"))
+}
+
+/// The bundle, offset and size of an indexed file.
+fn location<'a>(doc: &'a TantivyDocument, fields: &Fields) -> anyhow::Result<(&'a str, u64, u64)> {
+    let size = doc
+        .get_first(fields.size)
+        .and_then(|v| v.as_u64())
+        .context("file size")?;
+    let bundle = doc
+        .get_first(fields.bundle)
+        .and_then(|v| v.as_str())
+        .context("file bundle")?;
+    let offset = doc
+        .get_first(fields.offset)
+        .and_then(|v| v.as_u64())
+        .context("file offset")?;
+    Ok((bundle, offset, size))
+}
+
+pub fn read_u32<T: std::io::Read>(cur: &mut T) -> anyhow::Result<u32> {
+    let mut bytes = [0; 4];
+    cur.read_exact(&mut bytes[..])?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+pub fn read_u64<T: std::io::Read>(cur: &mut T) -> anyhow::Result<usize> {
+    let mut bytes = [0; 8];
+    cur.read_exact(&mut bytes[..])?;
+    Ok(u64::from_le_bytes(bytes) as usize)
+}
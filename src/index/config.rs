@@ -0,0 +1,74 @@
+use crate::index::analyzer::{is_analyzer, ANALYZER_VARS};
+use std::collections::{HashMap, HashSet};
+
+/// Prefix of the variables that switch analyzers on or off for one storage, e.g.
+/// `ANALYZERS_POE2=meshes,-text`.
+const STORAGE_PREFIX: &str = "ANALYZERS_";
+
+/// Optional indexing stages, switched on through the environment variables in [`ANALYZER_VARS`].
+/// Each stage is run by the analyzer of the same name.
+#[derive(Clone, Default)]
+pub struct IndexConfig {
+    /// Names of the analyzers switched on for every storage.
+    pub enabled: HashSet<String>,
+    /// Analyzers switched on or off for a single storage, overriding the ones above.
+    pub storages: HashMap<String, HashMap<String, bool>>,
+}
+
+impl IndexConfig {
+    pub fn from_env() -> Self {
+        Self {
+            enabled: ANALYZER_VARS
+                .iter()
+                .filter(|(var, _)| std::env::var(var).is_ok())
+                .map(|(_, name)| name.to_string())
+                .collect(),
+            storages: std::env::vars()
+                .filter_map(|(key, value)| {
+                    let storage = key.strip_prefix(STORAGE_PREFIX)?.to_lowercase();
+                    Some((storage, parse_overrides(&key, &value)))
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether the analyzer `name` runs on the versions of `storage`.
+    pub fn enabled(&self, name: &str, storage: &str) -> bool {
+        self.storages
+            .get(storage)
+            .and_then(|overrides| overrides.get(name))
+            .copied()
+            .unwrap_or_else(|| self.enabled.contains(name))
+    }
+
+    /// Whether the analyzer `name` runs on any storage.
+    pub fn enabled_anywhere(&self, name: &str) -> bool {
+        self.enabled.contains(name)
+            || self
+                .storages
+                .values()
+                .any(|overrides| overrides.get(name) == Some(&true))
+    }
+}
+
+/// Parses a comma-separated list of analyzer names, each prefixed with `-` to switch it off.
+/// Names of no analyzer are reported and ignored.
+fn parse_overrides(var: &str, value: &str) -> HashMap<String, bool> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| match name.strip_prefix('-') {
+            Some(name) => (name, false),
+            None => (name, true),
+        })
+        .filter(|&(name, _)| {
+            let known = is_analyzer(name);
+            if !known {
+                eprintln!("Ignoring unknown analyzer {name:?} in {var}");
+            }
+            known
+        })
+        .map(|(name, on)| (name.to_string(), on))
+        .collect()
+}
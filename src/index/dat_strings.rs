@@ -0,0 +1,152 @@
+use crate::formats::dat::{Dat, Schema};
+use crate::AppState;
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Arc;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::QueryParser;
+use tantivy::schema::{Field, SchemaBuilder};
+use tantivy::tokenizer::TokenizerManager;
+use tantivy::{schema, Index, IndexReader, IndexWriter, TantivyDocument, Term};
+use tempfile::TempDir;
+
+/// A separate index of the string values in dat tables, kept apart from the file index so its
+/// many small documents do not slow down browsing.
+pub struct DatStringsState {
+    pub index: Index,
+    pub fields: DatFields,
+    pub path: Option<TempDir>,
+    pub reader: IndexReader,
+    pub query_parser: QueryParser,
+}
+
+impl DatStringsState {
+    pub fn new() -> Self {
+        let path = TempDir::new().expect("Could not create dat strings directory.");
+        let mut state = Self::open_or_create(path.path());
+        state.path = Some(path);
+        state
+    }
+
+    pub fn open_or_create(path: &Path) -> Self {
+        if !path.exists() {
+            std::fs::create_dir_all(path).expect("Could not create directory");
+        }
+        let mut schema_builder = schema::Schema::builder();
+        let fields = DatFields::new(&mut schema_builder);
+        let directory = MmapDirectory::open(path).expect("Could not open dat strings directory.");
+        let index = Index::open_or_create(directory, schema_builder.build())
+            .expect("Could not open dat strings index.");
+        let reader = index.reader().expect("Could not create reader.");
+        let query_parser = QueryParser::new(
+            index.schema(),
+            vec![fields.value],
+            TokenizerManager::default(),
+        );
+        Self {
+            index,
+            fields,
+            path: None,
+            reader,
+            query_parser,
+        }
+    }
+}
+
+pub struct DatFields {
+    pub version: Field,
+    pub path: Field,
+    pub table: Field,
+    pub row: Field,
+    pub column: Field,
+    pub value: Field,
+}
+
+impl DatFields {
+    pub fn new(schema_builder: &mut SchemaBuilder) -> Self {
+        Self {
+            version: schema_builder.add_text_field("version", schema::STRING | schema::STORED),
+            path: schema_builder.add_text_field("path", schema::STRING | schema::STORED),
+            table: schema_builder.add_text_field("table", schema::STRING | schema::STORED),
+            row: schema_builder.add_u64_field("row", schema::STORED),
+            column: schema_builder.add_text_field("column", schema::STRING | schema::STORED),
+            value: schema_builder.add_text_field("value", schema::TEXT | schema::STORED),
+        }
+    }
+
+    pub fn version_term(&self, value: &str) -> Term {
+        Term::from_field_text(self.version, value)
+    }
+}
+
+/// Writes the string columns of the dat tables of a version into the dat strings index.
+pub struct DatStrings {
+    pub writer: IndexWriter,
+    state: &'static DatStringsState,
+    schema: Arc<Schema>,
+}
+
+impl DatStrings {
+    /// Returns `None` unless dat string indexing is enabled and a schema is configured.
+    pub fn new(state: &AppState, memory: usize) -> anyhow::Result<Option<Self>> {
+        if !state.config.enabled_anywhere("dat_strings") {
+            return Ok(None);
+        }
+        let Some(schema) = state.schema.clone() else {
+            eprintln!("INDEX_DAT_STRINGS is set but no DAT_SCHEMA is configured");
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            writer: state.dat_strings.index.writer(memory)?,
+            state: state.dat_strings,
+            schema,
+        }))
+    }
+
+    /// Deletes whatever an earlier attempt left of a version, so indexing it again adds no copies.
+    pub fn clear(&self, version: &str) {
+        self.writer
+            .delete_term(self.state.fields.version_term(version));
+    }
+
+    pub fn add(&self, storage: &str, version: &str, path: &str, data: &[u8]) -> anyhow::Result<()> {
+        let Some(table) =
+            Schema::table_name(path).and_then(|n| self.schema.table(n, Some(storage)))
+        else {
+            return Ok(());
+        };
+        let fields = &self.state.fields;
+        let dat = Dat::parse(data)?;
+        let offsets = table.offsets();
+        let names = table.column_names();
+        let columns = table
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|&(i, c)| c.typ == "string" && offsets[i] + c.width() <= dat.row_width)
+            .collect::<Vec<_>>();
+        for row in 0..dat.row_count {
+            for &(i, column) in &columns {
+                let strings = match dat.read(row, column, offsets[i])? {
+                    Value::String(s) => vec![s],
+                    Value::Array(values) => values
+                        .into_iter()
+                        .filter_map(|v| v.as_str().map(str::to_string))
+                        .collect(),
+                    _ => continue,
+                };
+                for s in strings.into_iter().filter(|s| !s.is_empty()) {
+                    let mut doc = TantivyDocument::new();
+                    doc.add_text(fields.version, version);
+                    doc.add_text(fields.path, path);
+                    doc.add_text(fields.table, &table.name);
+                    doc.add_u64(fields.row, row as u64);
+                    doc.add_text(fields.column, &names[i]);
+                    doc.add_text(fields.value, s);
+                    self.writer.add_document(doc)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
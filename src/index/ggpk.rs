@@ -1,17 +1,26 @@
+use crate::index::analyzer::{AnalyzerContext, Analyzers};
+use crate::index::bundle::{read_u32, read_u64, Bundles};
+use crate::index::config::IndexConfig;
+use crate::index::dat_strings::DatStrings;
+use crate::index::report::VersionReport;
 use crate::index::state::{EntryType, Fields};
-use anyhow::Context;
-use axum::body::Bytes;
-use csv::ReaderBuilder;
+use crate::index::virtual_entries::VirtualEntries;
 use std::collections::{BTreeMap, HashSet};
 use std::io::SeekFrom::Current;
 use std::io::{BufRead, Cursor, Read, Seek};
+use std::time::Instant;
 use tantivy::schema::Value;
 use tantivy::{IndexWriter, TantivyDocument};
 use url::Url;
 
-const PROCESS_SPRITE_SHEETS: bool = false;
-
-pub async fn index(version: &str, writer: &IndexWriter, fields: &Fields) -> anyhow::Result<()> {
+pub async fn index(
+    version: &str,
+    writer: &IndexWriter,
+    dat_strings: Option<&DatStrings>,
+    fields: &Fields,
+    config: &IndexConfig,
+    report: &mut VersionReport,
+) -> anyhow::Result<()> {
     let base = Url::parse(version)?;
     let url = base.join("Bundles2/_.index.bin")?;
     let response = reqwest::get(url).await?;
@@ -44,8 +53,9 @@ pub async fn index(version: &str, writer: &IndexWriter, fields: &Fields) -> anyh
     cur.seek(Current(path_rep_count * 20))?;
 
     let path_bundle = decompress(cur)?;
+    let analyzers = Analyzers::new(config, &report.storage);
     let mut dirs = HashSet::new();
-    let mut sprites = Vec::new();
+    let mut pending = Vec::new();
     decode_paths(path_bundle.as_slice(), &mut |filename| {
         let mut doc = to_doc(
             filename.as_str(),
@@ -56,23 +66,55 @@ pub async fn index(version: &str, writer: &IndexWriter, fields: &Fields) -> anyh
             &files,
         )?;
 
-        if let Some((_, ext)) = filename.rsplit_once('.') {
+        let ext = filename.rsplit_once('.').map(|(_, ext)| ext);
+        if let Some(ext) = ext {
             doc.add_text(fields.extension, ext);
-            if PROCESS_SPRITE_SHEETS && ext == "txt" && filename.starts_with("art") {
-                sprites.push(doc.clone());
-            }
         }
-        writer.add_document(doc)?;
+        if analyzers.for_file(&filename, ext).next().is_some() {
+            pending.push(doc);
+        } else {
+            writer.add_document(doc)?;
+        }
 
         add_dirs(filename.as_str(), &mut dirs);
 
         Ok(())
     })?;
 
-    for sprite in sprites {
-        if let Err(e) = add_sprite(sprite, writer, fields, &mut dirs).await {
-            eprintln!("Failed to index sprite: {e}");
+    let mut bundles = Bundles::new(version)?;
+    let mut entries = VirtualEntries::new(writer, fields, version, &mut dirs);
+    sort_by_bundle(&mut pending, fields);
+    for mut doc in pending {
+        let path = doc
+            .get_first(fields.path)
+            .and_then(|v| v.as_str())
+            .unwrap_or("<unknown file>")
+            .to_string();
+        let result = analyze(
+            &mut doc,
+            &analyzers,
+            fields,
+            dat_strings,
+            &mut entries,
+            &mut bundles,
+            report,
+        );
+        if let Err(e) = result.await {
+            report.content_errors.push(format!("{path}: {e:?}"));
+        }
+        // files with entries beneath them are listed as expandable
+        let children = entries.children(&path);
+        if children > 0 {
+            doc.add_u64(fields.children, children);
         }
+        writer.add_document(doc)?;
+    }
+
+    for (name, stats) in &report.analyzers {
+        println!(
+            "{version} {name}: {} files in {:.1}s, {} errors",
+            stats.files, stats.seconds, stats.errors
+        );
     }
 
     for filename in dirs {
@@ -121,137 +163,79 @@ fn to_doc(
     Ok(doc)
 }
 
-fn add_dirs(mut filename: &str, dirs: &mut HashSet<String>) {
-    while let Some((d, _)) = filename.rsplit_once('/') {
-        if dirs.insert(d.to_string()) {
-            filename = d;
-        } else {
-            break;
-        }
-    }
+/// Sorts documents so reading them in order downloads each bundle only once.
+fn sort_by_bundle(docs: &mut [TantivyDocument], fields: &Fields) {
+    docs.sort_by_cached_key(|doc| {
+        (
+            doc.get_first(fields.bundle)
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_string()),
+            doc.get_first(fields.offset).and_then(|v| v.as_u64()),
+        )
+    });
 }
 
-async fn add_sprite(
-    base: TantivyDocument,
-    writer: &IndexWriter,
+/// Reads a file and runs every analyzer for it, recording their timings and errors.
+async fn analyze(
+    doc: &mut TantivyDocument,
+    analyzers: &Analyzers,
     fields: &Fields,
-    dirs: &mut HashSet<String>,
+    dat_strings: Option<&DatStrings>,
+    entries: &mut VirtualEntries<'_>,
+    bundles: &mut Bundles,
+    report: &mut VersionReport,
 ) -> anyhow::Result<()> {
-    let mut reader = get_data(&base, fields).await?;
-
-    let sprite_txt = base.get_first(fields.path).and_then(|f| f.as_str());
-
-    for record in reader.deserialize::<(String, String, u64, u64, u64, u64)>() {
-        let (mut filename, mut source, x, y, x2, y2) = match record {
-            Err(e) => {
-                eprintln!(
-                    "Error parsing record from {}: {}",
-                    sprite_txt.unwrap_or("<unknown file>"),
-                    e
-                );
-                continue;
-            }
-            Ok(r) => r,
-        };
-
-        filename = filename.to_lowercase();
-        source = source.to_lowercase();
-
-        let mut doc = TantivyDocument::new();
-        for v in base.get_all(fields.version) {
-            doc.add_field_value(fields.version, v.clone());
-        }
-
-        doc.add_text(fields.typ, EntryType::SPRITE);
-        doc.add_text(fields.path, filename.clone());
-        let filename = filename.as_str();
-        let (dir, name) = filename
-            .rsplit_once('/')
-            .unwrap_or(("art/sprites", filename));
-        doc.add_text(fields.name, name);
-        doc.add_text(fields.parent, dir);
-
-        doc.add_text(fields.sprite_sheet, source);
-        if let Some(txt) = sprite_txt {
-            doc.add_text(fields.sprite_txt, txt)
+    let data = bundles.read_doc(doc, fields).await?;
+    let text_field = |field| {
+        doc.get_first(field)
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .unwrap_or_default()
+    };
+    let (path, ext, version) = (
+        text_field(fields.path),
+        text_field(fields.extension),
+        text_field(fields.version),
+    );
+    for analyzer in analyzers.for_file(&path, Some(&ext)) {
+        let start = Instant::now();
+        let result = analyzer.analyze(
+            &mut AnalyzerContext {
+                fields,
+                path: &path,
+                ext: &ext,
+                version: &version,
+                dat_strings,
+                entries,
+                report,
+            },
+            doc,
+            &data,
+        );
+        let stats = report
+            .analyzers
+            .entry(analyzer.name().to_string())
+            .or_default();
+        stats.files += 1;
+        stats.seconds += start.elapsed().as_secs_f64();
+        if let Err(e) = result {
+            stats.errors += 1;
+            report
+                .content_errors
+                .push(format!("{path}: {}: {e:?}", analyzer.name()));
         }
-        // min and abs_diff not really necessary as x1 and y1 should always be top left, but what's the harm
-        doc.add_u64(fields.sprite_x, x.min(x2));
-        doc.add_u64(fields.sprite_y, y.min(y2));
-        doc.add_u64(fields.sprite_w, x.abs_diff(x2) + 1);
-        doc.add_u64(fields.sprite_h, y.abs_diff(y2) + 1);
-
-        writer.add_document(doc)?;
-
-        add_dirs(filename, dirs);
     }
-
     Ok(())
 }
 
-async fn get_data(
-    doc: &TantivyDocument,
-    fields: &Fields,
-) -> anyhow::Result<csv::Reader<Cursor<Bytes>>> {
-    let size = doc
-        .get_first(fields.size)
-        .and_then(|v| v.as_u64())
-        .context("sprite size")?;
-    let bundle_name = doc
-        .get_first(fields.bundle)
-        .and_then(|v| v.as_str())
-        .context("sprite bundle")?;
-    let bundle_size = doc
-        .get_first(fields.bundle_size)
-        .and_then(|v| v.as_u64())
-        .context("sprite bundle size")?;
-    let bundle_offset = doc
-        .get_first(fields.offset)
-        .and_then(|v| v.as_u64())
-        .context("sprite offset")?;
-    let storage = doc
-        .get_first(fields.version)
-        .and_then(|v| v.as_str())
-        .context("sprite storage")?;
-    let version = storage
-        .split('/')
-        .filter(|&v| !v.is_empty())
-        .next_back()
-        .context("sprite version")?;
-
-    let frontend =
-        std::env::var("FRONTEND_URL").unwrap_or_else(|_| "https://ggpk.exposed".to_string());
-    let mut url = Url::parse(frontend.as_str()).context(frontend)?;
-    url.path_segments_mut()
-        .map_err(|_| anyhow::Error::msg("path segments failed"))?
-        .push(version)
-        .push("sprite.txt");
-
-    {
-        let mut query = url.query_pairs_mut();
-
-        // some parameters are not used to fetch data, but must be non-empty to avoid validation errors
-        // if validation fails the frontend will fall back to this server's index, which will not be ready
-        // until the current process is complete
-        query.append_pair("path", "sprite");
-        query.append_pair("dirname", "/");
-        query.append_pair("basename", "sprite");
-        query.append_pair("extension", "txt");
-        query.append_pair("type", "file");
-        query.append_pair("mime_type", "text/plain");
-
-        query.append_pair("storage", storage);
-        query.append_pair("file_size", size.to_string().as_str());
-        query.append_pair("bundle_offset", bundle_offset.to_string().as_str());
-        query.append_pair("bundle[size]", bundle_size.to_string().as_str());
-        query.append_pair("bundle[name]", bundle_name);
+pub(crate) fn add_dirs(mut filename: &str, dirs: &mut HashSet<String>) {
+    while let Some((d, _)) = filename.rsplit_once('/') {
+        if dirs.insert(d.to_string()) {
+            filename = d;
+        } else {
+            break;
+        }
     }
-
-    let reader = Cursor::new(reqwest::get(url).await?.bytes().await?);
-    Ok(ReaderBuilder::new()
-        .has_headers(false)
-        .delimiter(b' ')
-        .from_reader(reader))
 }
 
 fn decompress<T: Read>(f: &mut T) -> anyhow::Result<Vec<u8>> {
@@ -321,15 +305,3 @@ fn decode_paths<CB: FnMut(String) -> anyhow::Result<()>>(
     }
     Ok(())
 }
-
-fn read_u32<T: Read>(cur: &mut T) -> anyhow::Result<u32> {
-    let mut bytes = [0; 4];
-    cur.read_exact(&mut bytes[..])?;
-    Ok(u32::from_le_bytes(bytes))
-}
-
-fn read_u64<T: Read>(cur: &mut T) -> anyhow::Result<usize> {
-    let mut bytes = [0; 8];
-    cur.read_exact(&mut bytes[..])?;
-    Ok(u64::from_le_bytes(bytes) as usize)
-}
@@ -1,4 +1,10 @@
+pub mod analyzer;
+pub mod bundle;
 pub mod collector;
+pub mod config;
+pub mod dat_strings;
 pub mod ggpk;
+pub mod report;
 pub mod state;
 pub mod updater;
+pub mod virtual_entries;
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const REPORT_FILE: &str = "report.json";
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct BuildReport {
+    pub versions: BTreeMap<String, VersionReport>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct VersionReport {
+    pub storage: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sprite_errors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub content_errors: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub analyzers: BTreeMap<String, AnalyzerReport>,
+}
+
+/// What one analyzer did over a version.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct AnalyzerReport {
+    pub files: u64,
+    pub errors: u64,
+    /// Time spent analyzing, not counting reading the files.
+    pub seconds: f64,
+}
+
+impl VersionReport {
+    pub fn new(storage: &str) -> Self {
+        Self {
+            storage: storage.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl BuildReport {
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join(REPORT_FILE);
+        if !path.exists() {
+            return Self::default();
+        }
+        std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(serde_json::from_str(&content)?))
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to load build report: {e:?}");
+                Self::default()
+            })
+    }
+
+    pub fn save(&self, dir: &Path) -> anyhow::Result<()> {
+        std::fs::write(dir.join(REPORT_FILE), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, version: &str, report: VersionReport) {
+        self.versions.insert(version.to_string(), report);
+    }
+
+    pub fn remove(&mut self, version: &str) {
+        self.versions.remove(version);
+    }
+}
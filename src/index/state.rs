@@ -11,6 +11,8 @@ impl EntryType {
     pub const FILE: &'static str = "file";
     pub const DIR: &'static str = "dir";
     pub const SPRITE: &'static str = "sprite";
+    /// A sound embedded in an FMOD `.bank` file, listed beneath the bank.
+    pub const SOUND: &'static str = "sound";
 }
 
 pub struct IndexState {
@@ -19,6 +21,7 @@ pub struct IndexState {
     pub path: Option<TempDir>,
     pub reader: IndexReader,
     pub query_parser: QueryParser,
+    pub content_parser: QueryParser,
 }
 
 impl Default for IndexState {
@@ -70,12 +73,19 @@ impl IndexState {
         query_parser.set_field_fuzzy(fields.name, true, 0, false);
         query_parser.allow_regexes();
 
+        let content_parser = QueryParser::new(
+            index.schema(),
+            vec![fields.content],
+            TokenizerManager::default(),
+        );
+
         Self {
             path,
             index,
             reader,
             fields,
             query_parser,
+            content_parser,
         }
     }
 }
@@ -97,6 +107,23 @@ pub struct Fields {
     pub sprite_y: Field,
     pub sprite_w: Field,
     pub sprite_h: Field,
+    pub texture_width: Field,
+    pub texture_height: Field,
+    pub texture_mips: Field,
+    pub texture_format: Field,
+    pub content: Field,
+    pub stat_id: Field,
+    pub references: Field,
+    pub mesh_vertices: Field,
+    pub mesh_triangles: Field,
+    pub mesh_bbox: Field,
+    pub mesh_materials: Field,
+    pub audio_duration: Field,
+    pub audio_channels: Field,
+    pub audio_sample_rate: Field,
+    pub audio_format: Field,
+    pub children: Field,
+    pub content_hash: Field,
 }
 
 impl Fields {
@@ -112,12 +139,59 @@ impl Fields {
         let size = schema_builder.add_u64_field("size", schema::STORED);
         let bundle = schema_builder.add_text_field("bundle", schema::STORED);
         let bundle_size = schema_builder.add_u64_field("bundle_size", schema::STORED);
-        let sprite_sheet = schema_builder.add_text_field("sprite_sheet", schema::STORED);
+        let sprite_sheet =
+            schema_builder.add_text_field("sprite_sheet", schema::STRING | schema::STORED);
         let sprite_txt = schema_builder.add_text_field("sprite_txt", schema::STORED);
         let sprite_x = schema_builder.add_u64_field("sprite_x", schema::STORED);
         let sprite_y = schema_builder.add_u64_field("sprite_y", schema::STORED);
         let sprite_w = schema_builder.add_u64_field("sprite_w", schema::STORED);
         let sprite_h = schema_builder.add_u64_field("sprite_h", schema::STORED);
+        let texture_width = schema_builder.add_u64_field(
+            "texture_width",
+            schema::INDEXED | schema::STORED | schema::FAST,
+        );
+        let texture_height = schema_builder.add_u64_field(
+            "texture_height",
+            schema::INDEXED | schema::STORED | schema::FAST,
+        );
+        let texture_mips = schema_builder.add_u64_field(
+            "texture_mips",
+            schema::INDEXED | schema::STORED | schema::FAST,
+        );
+        let texture_format = schema_builder.add_text_field(
+            "texture_format",
+            schema::STRING | schema::STORED | schema::FAST,
+        );
+
+        let content = schema_builder.add_text_field("content", schema::TEXT);
+        let stat_id = schema_builder.add_text_field("stat_id", schema::STRING);
+        let references =
+            schema_builder.add_text_field("references", schema::STRING | schema::STORED);
+        let mesh_vertices = schema_builder.add_u64_field(
+            "mesh_vertices",
+            schema::INDEXED | schema::STORED | schema::FAST,
+        );
+        let mesh_triangles = schema_builder.add_u64_field(
+            "mesh_triangles",
+            schema::INDEXED | schema::STORED | schema::FAST,
+        );
+        let mesh_bbox = schema_builder.add_f64_field("mesh_bbox", schema::STORED);
+        let mesh_materials =
+            schema_builder.add_text_field("mesh_materials", schema::STRING | schema::STORED);
+        let audio_duration = schema_builder.add_f64_field(
+            "audio_duration",
+            schema::INDEXED | schema::STORED | schema::FAST,
+        );
+        let audio_channels = schema_builder.add_u64_field("audio_channels", schema::STORED);
+        let audio_sample_rate = schema_builder.add_u64_field("audio_sample_rate", schema::STORED);
+        let audio_format =
+            schema_builder.add_text_field("audio_format", schema::STRING | schema::STORED);
+        let children = schema_builder
+            .add_u64_field("children", schema::INDEXED | schema::STORED | schema::FAST);
+        let content_hash = schema_builder.add_u64_field(
+            "content_hash",
+            schema::INDEXED | schema::STORED | schema::FAST,
+        );
 
         Self {
             path,
@@ -136,6 +210,23 @@ impl Fields {
             sprite_y,
             sprite_w,
             sprite_h,
+            texture_width,
+            texture_height,
+            texture_mips,
+            texture_format,
+            content,
+            stat_id,
+            references,
+            mesh_vertices,
+            mesh_triangles,
+            mesh_bbox,
+            mesh_materials,
+            audio_duration,
+            audio_channels,
+            audio_sample_rate,
+            audio_format,
+            children,
+            content_hash,
         }
     }
 
@@ -1,9 +1,12 @@
-use crate::index::state::IndexState;
+use crate::index::config::IndexConfig;
+use crate::index::dat_strings::DatStrings;
+use crate::index::report::VersionReport;
+use crate::index::state::{Fields, IndexState};
 use crate::AppState;
 use std::io::ErrorKind;
 use std::sync::Arc;
 use std::time::Duration;
-use tantivy::TantivyDocument;
+use tantivy::{IndexWriter, TantivyDocument};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::sync::RwLock;
@@ -16,7 +19,7 @@ pub async fn watch(state: AppState) {
         let mut poe1_updated = Vec::with_capacity(1);
         if check_urls("patch.pathofexile.com:12995", &mut poe1_updated).await {
             let prev = { state.poe1.read().await.clone() };
-            if update_storage(&state, &prev, poe1_updated, &state.poe1).await {
+            if update_storage(&state, "poe1", &prev, poe1_updated, &state.poe1).await {
                 println!("PoE1 updated");
             }
         }
@@ -24,7 +27,7 @@ pub async fn watch(state: AppState) {
         let mut poe2_updated = Vec::with_capacity(1);
         if check_urls("patch.pathofexile2.com:13060", &mut poe2_updated).await {
             let prev = { state.poe2.read().await.clone() };
-            if update_storage(&state, &prev, poe2_updated, &state.poe2).await {
+            if update_storage(&state, "poe2", &prev, poe2_updated, &state.poe2).await {
                 println!("PoE2 updated");
             }
         }
@@ -33,6 +36,7 @@ pub async fn watch(state: AppState) {
 
 async fn update_storage(
     state: &AppState,
+    storage: &str,
     prev: &[String],
     updated: Vec<String>,
     lock: &Arc<RwLock<Vec<String>>>,
@@ -40,16 +44,29 @@ async fn update_storage(
     let removed = subtract(prev, &updated);
     let added = subtract(&updated, prev);
 
-    if (!removed.is_empty() || !added.is_empty())
-        && reindex(state.index, removed, added).await
-            .map_err(|e| eprintln!("indexing failed: {e:?}"))
-            .is_ok()
-        {
-            let mut urls = lock.write().await;
-            *urls = updated;
-            return true;
+    if removed.is_empty() && added.is_empty() {
+        return false;
+    }
+    let indexed = match reindex(state, storage, &removed, &added).await {
+        Ok(indexed) => indexed,
+        Err(e) => {
+            eprintln!("indexing failed: {e:?}");
+            return false;
         }
-    false
+    };
+
+    // versions that failed to index are left out, so they are retried on the next check
+    {
+        let mut urls = lock.write().await;
+        *urls = updated
+            .into_iter()
+            .filter(|url| prev.contains(url) || indexed.contains(url))
+            .collect();
+    }
+    if let Err(e) = state.save().await {
+        eprintln!("Failed to save index state: {e:?}");
+    }
+    !removed.is_empty() || !indexed.is_empty()
 }
 
 fn subtract(prev: &[String], updated: &[String]) -> Vec<String> {
@@ -60,21 +77,97 @@ fn subtract(prev: &[String], updated: &[String]) -> Vec<String> {
 }
 
 async fn reindex(
-    IndexState { index, fields, .. }: &IndexState,
-    removed: Vec<String>,
-    added: Vec<String>,
-) -> anyhow::Result<()> {
+    state: &AppState,
+    storage: &str,
+    removed: &[String],
+    added: &[String],
+) -> anyhow::Result<Vec<String>> {
+    let IndexState { index, fields, .. } = state.index;
     println!("Updating index - added {added:?}, removed {removed:?}");
     let mut writer = index.writer::<TantivyDocument>(50_000_000)?;
-    for r in &removed {
-        writer.delete_term(fields.version_term(r.as_str()));
+    let mut dat_strings = DatStrings::new(state, 50_000_000)?;
+    if !removed.is_empty() {
+        for r in removed {
+            writer.delete_term(fields.version_term(r.as_str()));
+        }
+        writer.commit()?;
+        if let Some(dat_strings) = dat_strings.as_mut() {
+            let dat_fields = &state.dat_strings.fields;
+            for r in removed {
+                dat_strings
+                    .writer
+                    .delete_term(dat_fields.version_term(r.as_str()));
+            }
+            dat_strings.writer.commit()?;
+        }
     }
-    for r in &added {
-        crate::index::ggpk::index(r.as_str(), &writer, fields).await?
+
+    let mut indexed = Vec::with_capacity(added.len());
+    let mut results = Vec::with_capacity(added.len());
+    for r in added {
+        let result = index_version(
+            &mut writer,
+            dat_strings.as_mut(),
+            fields,
+            &state.config,
+            storage,
+            r,
+        )
+        .await;
+        if result.succeeded() {
+            indexed.push(r.clone());
+        }
+        results.push((r, result));
+    }
+    // readers of the report should not wait on the downloads above
+    let mut report = state.report.write().await;
+    for r in removed {
+        report.remove(r);
+    }
+    for (r, result) in results {
+        report.record(r, result);
     }
-    writer.commit()?;
     println!("Index updated");
-    Ok(())
+    Ok(indexed)
+}
+
+/// Indexes and commits a single version, discarding its documents if anything fails.
+pub async fn index_version(
+    writer: &mut IndexWriter,
+    mut dat_strings: Option<&mut DatStrings>,
+    fields: &Fields,
+    config: &IndexConfig,
+    storage: &str,
+    version: &str,
+) -> VersionReport {
+    let mut report = VersionReport::new(storage);
+    // a failed main commit cannot undo the dat strings commit before it
+    if let Some(d) = dat_strings.as_deref() {
+        d.clear(version);
+    }
+    let dat = dat_strings.as_deref();
+    let result =
+        match crate::index::ggpk::index(version, writer, dat, fields, config, &mut report).await {
+            // dat strings go first, they are only found through versions the file index lists
+            Ok(()) => dat_strings
+                .as_mut()
+                .map_or(Ok(0), |d| d.writer.commit())
+                .and_then(|_| writer.commit())
+                .map(|_| ())
+                .map_err(anyhow::Error::from),
+            Err(e) => Err(e),
+        };
+    if let Err(e) = result {
+        eprintln!("Failed to index {version}: {e:?}");
+        report.error = Some(format!("{e:?}"));
+        if let Err(e) = writer.rollback() {
+            eprintln!("Failed to roll back {version}: {e:?}");
+        }
+        if let Some(Err(e)) = dat_strings.map(|d| d.writer.rollback()) {
+            eprintln!("Failed to roll back dat strings of {version}: {e:?}");
+        }
+    }
+    report
 }
 
 async fn check_urls(addr: &'static str, out: &mut Vec<String>) -> bool {
@@ -92,7 +185,10 @@ async fn check_urls(addr: &'static str, out: &mut Vec<String>) -> bool {
     }
 }
 
-pub async fn try_check_urls(addr: &'static str, out: &mut Vec<String>) -> Result<(), std::io::Error> {
+pub async fn try_check_urls(
+    addr: &'static str,
+    out: &mut Vec<String>,
+) -> Result<(), std::io::Error> {
     let mut stream = TcpStream::connect(addr).await?;
     stream.write_all(&[1, 7]).await?;
     let mut buf = [0; 1000];
@@ -0,0 +1,84 @@
+use crate::index::ggpk::add_dirs;
+use crate::index::state::Fields;
+use std::collections::{HashMap, HashSet};
+use tantivy::{IndexWriter, TantivyDocument};
+
+/// Entries cut from the files of a version rather than stored in its bundles, such as the sprites
+/// of a sprite sheet or the sounds of a sound bank.
+///
+/// An entry either sits in a directory of its own, which is created as needed, or beneath the file
+/// it was cut from, which browse then lists as expandable.
+pub struct VirtualEntries<'a> {
+    writer: &'a IndexWriter,
+    fields: &'a Fields,
+    version: &'a str,
+    dirs: &'a mut HashSet<String>,
+    children: HashMap<String, u64>,
+}
+
+impl<'a> VirtualEntries<'a> {
+    pub fn new(
+        writer: &'a IndexWriter,
+        fields: &'a Fields,
+        version: &'a str,
+        dirs: &'a mut HashSet<String>,
+    ) -> Self {
+        Self {
+            writer,
+            fields,
+            version,
+            dirs,
+            children: HashMap::new(),
+        }
+    }
+
+    /// Adds an entry of type `typ` indexed under `path` and listed as `name` in the directory
+    /// `parent`, along with the fields already in `doc`.
+    pub fn add(
+        &mut self,
+        parent: &str,
+        name: &str,
+        path: &str,
+        typ: &str,
+        doc: TantivyDocument,
+    ) -> anyhow::Result<()> {
+        self.write(parent, name, path, typ, doc)?;
+        add_dirs(&format!("{parent}/{name}"), self.dirs);
+        Ok(())
+    }
+
+    /// Adds an entry of type `typ` named `name` beneath the file at `parent`.
+    pub fn add_child(
+        &mut self,
+        parent: &str,
+        name: &str,
+        typ: &str,
+        doc: TantivyDocument,
+    ) -> anyhow::Result<()> {
+        self.write(parent, name, &format!("{parent}/{name}"), typ, doc)?;
+        *self.children.entry(parent.to_string()).or_default() += 1;
+        Ok(())
+    }
+
+    /// How many entries were added beneath the file at `path`.
+    pub fn children(&self, path: &str) -> u64 {
+        self.children.get(path).copied().unwrap_or_default()
+    }
+
+    fn write(
+        &self,
+        parent: &str,
+        name: &str,
+        path: &str,
+        typ: &str,
+        mut doc: TantivyDocument,
+    ) -> anyhow::Result<()> {
+        doc.add_text(self.fields.version, self.version);
+        doc.add_text(self.fields.typ, typ);
+        doc.add_text(self.fields.path, path);
+        doc.add_text(self.fields.name, name);
+        doc.add_text(self.fields.parent, parent);
+        self.writer.add_document(doc)?;
+        Ok(())
+    }
+}
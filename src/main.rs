@@ -1,58 +1,79 @@
+#![allow(clippy::result_large_err)]
+
+use crate::formats::dat::Schema;
+use crate::index::bundle::BundleCache;
+use crate::index::config::IndexConfig;
+use crate::index::dat_strings::{DatStrings, DatStringsState};
+use crate::index::report::BuildReport;
 use crate::index::state::IndexState;
 use axum::{routing::get, Router};
 use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+mod formats;
 mod index;
 mod routes;
 
 #[tokio::main]
 async fn main() {
-    let index_dir = std::env::var("INDEX_DIR").ok().map(std::path::PathBuf::from);
+    let index_dir = std::env::var("INDEX_DIR").ok().map(PathBuf::from);
     let build_index = std::env::var("BUILD_INDEX").is_ok();
     let read_only = std::env::var("READ_ONLY").is_ok() || build_index;
 
-    let state = if let Some(dir) = index_dir.as_ref() {
+    if build_index && index_dir.is_none() {
+        panic!("INDEX_DIR is required for BUILD_INDEX");
+    }
+
+    let state = if let Some(dir) = index_dir {
         if build_index {
-            AppState::create(dir.clone())
+            AppState::create(dir)
         } else {
-            AppState::open(dir.clone())
+            AppState::open(dir)
         }
     } else {
         AppState::new()
     };
 
     if build_index {
-        let dir = index_dir.expect("INDEX_DIR is required for BUILD_INDEX");
-        let mut poe1_urls = Vec::new();
-        index::updater::try_check_urls("patch.pathofexile.com:12995", &mut poe1_urls)
-            .await
-            .expect("Failed to fetch PoE1 URLs");
-        let mut poe2_urls = Vec::new();
-        index::updater::try_check_urls("patch.pathofexile2.com:13060", &mut poe2_urls)
-            .await
-            .expect("Failed to fetch PoE2 URLs");
-
-        println!("Building index for PoE1 URLs: {poe1_urls:?}, PoE2 URLs: {poe2_urls:?}");
         let mut writer = state
             .index
             .index
             .writer::<tantivy::TantivyDocument>(100_000_000)
             .expect("Failed to create writer");
-        for url in poe1_urls.iter().chain(poe2_urls.iter()) {
-            index::ggpk::index(url, &writer, &state.index.fields)
-                .await
-                .expect("Failed to index");
+        let mut dat_strings =
+            DatStrings::new(&state, 100_000_000).expect("Failed to create dat strings writer");
+        for (storage, addr, lock) in [
+            ("poe1", "patch.pathofexile.com:12995", &state.poe1),
+            ("poe2", "patch.pathofexile2.com:13060", &state.poe2),
+        ] {
+            let mut urls = Vec::new();
+            if let Err(e) = index::updater::try_check_urls(addr, &mut urls).await {
+                eprintln!("Failed to fetch {storage} URLs: {e:?}");
+            }
+            println!("Building index for {storage} URLs: {urls:?}");
+
+            let mut indexed = Vec::with_capacity(urls.len());
+            for url in urls {
+                let result = index::updater::index_version(
+                    &mut writer,
+                    dat_strings.as_mut(),
+                    &state.index.fields,
+                    &state.config,
+                    storage,
+                    &url,
+                )
+                .await;
+                if result.succeeded() {
+                    indexed.push(url.clone());
+                }
+                state.report.write().await.record(&url, result);
+            }
+            *lock.write().await = indexed;
         }
-        writer.commit().expect("Failed to commit");
-
-        let mut map = std::collections::HashMap::new();
-        map.insert("poe1", poe1_urls);
-        map.insert("poe2", poe2_urls);
 
-        std::fs::write(dir.join("urls.json"), serde_json::to_string(&map).unwrap())
-            .expect("Failed to save URLs");
+        state.save().await.expect("Failed to save URLs");
 
         println!("Index build complete");
         return;
@@ -64,6 +85,23 @@ async fn main() {
 
     let app = Router::new()
         .route("/files", get(routes::browse::handler))
+        .route("/sprites", get(routes::sprites::handler))
+        .route("/sprite.png", get(routes::image::sprite_handler))
+        .route("/texture.png", get(routes::image::texture_handler))
+        .route("/model.gltf", get(routes::model::handler))
+        .route("/audio", get(routes::audio::handler))
+        .route("/preview", get(routes::preview::handler))
+        .route("/hexdump", get(routes::hexdump::handler))
+        .route("/dat", get(routes::dat::handler))
+        .route("/dat-diff", get(routes::dat::diff_handler))
+        .route("/dat-references", get(routes::dat::references_handler))
+        .route("/dat-search", get(routes::dat::search_handler))
+        .route("/stat-descriptions", get(routes::stats::handler))
+        .route("/stat-search", get(routes::stats::search_handler))
+        .route("/template", get(routes::template::handler))
+        .route("/passive-tree", get(routes::psg::handler))
+        .route("/references", get(routes::references::handler))
+        .route("/referenced-by", get(routes::references::referenced_by_handler))
         .route("/version", get(routes::version::handler))
         .route("/check-version", get(routes::version::socket_handler))
         .with_state(state);
@@ -82,17 +120,38 @@ pub struct AppState {
     pub poe1: Arc<RwLock<Vec<String>>>,
     pub poe2: Arc<RwLock<Vec<String>>>,
     pub index: &'static IndexState,
+    pub dat_strings: &'static DatStringsState,
+    pub report: Arc<RwLock<BuildReport>>,
+    pub config: IndexConfig,
+    pub schema: Option<Arc<Schema>>,
+    pub dir: Option<PathBuf>,
+    pub bundles: Arc<BundleCache>,
 }
 
+/// How many bytes of downloaded and decompressed bundles are kept between requests.
+const BUNDLE_CACHE_BYTES: usize = 1 << 30;
+
 impl AppState {
     fn new() -> Self {
         let poe1 = Arc::new(RwLock::new(Vec::<String>::new()));
         let poe2 = Arc::new(RwLock::new(Vec::<String>::new()));
         let index = Box::leak(Box::new(IndexState::new()));
-        Self { poe1, poe2, index }
+        let dat_strings = Box::leak(Box::new(DatStringsState::new()));
+        let report = Arc::new(RwLock::new(BuildReport::default()));
+        Self {
+            poe1,
+            poe2,
+            index,
+            dat_strings,
+            report,
+            config: IndexConfig::from_env(),
+            schema: Schema::from_env(),
+            dir: None,
+            bundles: Arc::new(BundleCache::new(BUNDLE_CACHE_BYTES)),
+        }
     }
 
-    fn open(path: std::path::PathBuf) -> Self {
+    fn open(path: PathBuf) -> Self {
         let urls_path = path.join("urls.json");
         let (poe1, poe2) = if urls_path.exists() {
             let content = std::fs::read_to_string(urls_path).expect("Failed to read URLs");
@@ -107,21 +166,67 @@ impl AppState {
         };
         let poe1 = Arc::new(RwLock::new(poe1));
         let poe2 = Arc::new(RwLock::new(poe2));
-        let index = Box::leak(Box::new(IndexState::open(path)));
-        Self { poe1, poe2, index }
+        let report = Arc::new(RwLock::new(BuildReport::load(&path)));
+        let index = Box::leak(Box::new(IndexState::open(path.clone())));
+        let dat_strings = Box::leak(Box::new(DatStringsState::open_or_create(&path.join("dat"))));
+        Self {
+            poe1,
+            poe2,
+            index,
+            dat_strings,
+            report,
+            config: IndexConfig::from_env(),
+            schema: Schema::from_env(),
+            dir: Some(path),
+            bundles: Arc::new(BundleCache::new(BUNDLE_CACHE_BYTES)),
+        }
     }
 
-    fn create(path: std::path::PathBuf) -> Self {
+    fn create(path: PathBuf) -> Self {
         let poe1 = Arc::new(RwLock::new(Vec::<String>::new()));
         let poe2 = Arc::new(RwLock::new(Vec::<String>::new()));
-        let index = Box::leak(Box::new(IndexState::create(path)));
-        Self { poe1, poe2, index }
+        let report = Arc::new(RwLock::new(BuildReport::default()));
+        let index = Box::leak(Box::new(IndexState::create(path.clone())));
+        let dat_strings = Box::leak(Box::new(DatStringsState::open_or_create(&path.join("dat"))));
+        Self {
+            poe1,
+            poe2,
+            index,
+            dat_strings,
+            report,
+            config: IndexConfig::from_env(),
+            schema: Schema::from_env(),
+            dir: Some(path),
+            bundles: Arc::new(BundleCache::new(BUNDLE_CACHE_BYTES)),
+        }
+    }
+
+    /// Persists the indexed URLs and the build report, if the index lives in a directory.
+    pub async fn save(&self) -> anyhow::Result<()> {
+        let Some(dir) = self.dir.as_ref() else {
+            return Ok(());
+        };
+        let mut map = std::collections::HashMap::new();
+        map.insert("poe1", self.poe1.read().await.clone());
+        map.insert("poe2", self.poe2.read().await.clone());
+        std::fs::write(dir.join("urls.json"), serde_json::to_string(&map)?)?;
+        self.report.read().await.save(dir)
     }
 
     pub async fn storages(&self) -> Vec<String> {
         vec!["poe1".to_string(), "poe2".to_string()]
     }
 
+    /// The storage a version is currently listed under, if any.
+    pub async fn storage_of(&self, version: &str) -> Option<String> {
+        for storage in self.storages().await {
+            if self.urls(&storage).await.iter().any(|url| url == version) {
+                return Some(storage);
+            }
+        }
+        None
+    }
+
     pub async fn urls(&self, storage: &str) -> Vec<String> {
         match storage {
             "poe1" => self.poe1.read().await.clone(),
@@ -0,0 +1,150 @@
+use crate::formats::audio::AUDIO_EXT;
+use crate::index::state::{EntryType, IndexState};
+use crate::routes::file::{doc_path, failure, lookup, read};
+use crate::AppState;
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use std::ops::RangeInclusive;
+use tantivy::schema::Value;
+
+#[derive(Deserialize)]
+pub struct Params {
+    adapter: Option<String>,
+    #[serde(default)]
+    path: String,
+}
+
+/// Serves an audio file as is, honouring a single byte range so that players can seek.
+pub async fn handler(
+    Query(Params { adapter, path }): Query<Params>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, Response> {
+    let IndexState { fields, .. } = state.index;
+    let doc = lookup(&state, adapter, path, EntryType::FILE).await?;
+    let ext = doc.get_first(fields.extension).and_then(|v| v.as_str());
+    if !ext.is_some_and(|ext| AUDIO_EXT.contains(&ext)) {
+        return Err(failure(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "file is not an audio file",
+        ));
+    }
+    let mime = mime_guess::from_path(doc_path(fields, &doc)).first_or_octet_stream();
+    let data = read(&state, &doc).await?;
+    let len = data.len();
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| parse_range(v, len));
+    let Some(range) = range else {
+        return Ok((
+            [
+                (header::CONTENT_TYPE, mime.to_string()),
+                (header::ACCEPT_RANGES, "bytes".to_string()),
+            ],
+            data,
+        )
+            .into_response());
+    };
+    let Some(range) = range else {
+        let message = format!("range outside of the {len} byte file");
+        return Err((
+            [(header::CONTENT_RANGE, format!("bytes */{len}"))],
+            failure(StatusCode::RANGE_NOT_SATISFIABLE, message),
+        )
+            .into_response());
+    };
+    Ok((
+        StatusCode::PARTIAL_CONTENT,
+        [
+            (header::CONTENT_TYPE, mime.to_string()),
+            (header::ACCEPT_RANGES, "bytes".to_string()),
+            (
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{len}", range.start(), range.end()),
+            ),
+        ],
+        data[range].to_vec(),
+    )
+        .into_response())
+}
+
+/// Parses `bytes=start-end`, `bytes=start-` or `bytes=-suffix` against a file of `len` bytes.
+///
+/// Returns `None` for headers that should be ignored, such as multiple ranges, and `Some(None)`
+/// for ranges that do not overlap the file.
+fn parse_range(header: &str, len: usize) -> Option<Option<RangeInclusive<usize>>> {
+    let spec = header.strip_prefix("bytes=")?.trim();
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", "") => return None,
+        ("", suffix) => {
+            let suffix = suffix.parse::<usize>().ok()?;
+            (len.saturating_sub(suffix), len.checked_sub(1))
+        }
+        (start, "") => (start.parse().ok()?, len.checked_sub(1)),
+        (start, end) => {
+            let (start, end): (usize, usize) = (start.parse().ok()?, end.parse().ok()?);
+            if end < start {
+                return None;
+            }
+            (start, Some(end.min(len.saturating_sub(1))))
+        }
+    };
+    Some(
+        end.filter(|&end| start <= end && start < len)
+            .map(|end| start..=end),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_range;
+
+    #[test]
+    fn closed_and_open_ranges() {
+        assert_eq!(parse_range("bytes=0-9", 10), Some(Some(0..=9)));
+        assert_eq!(parse_range("bytes=2-4", 10), Some(Some(2..=4)));
+        assert_eq!(parse_range("bytes=5-", 10), Some(Some(5..=9)));
+        assert_eq!(parse_range("bytes=8-100", 10), Some(Some(8..=9)));
+    }
+
+    #[test]
+    fn suffix_ranges() {
+        assert_eq!(parse_range("bytes=-3", 10), Some(Some(7..=9)));
+        assert_eq!(parse_range("bytes=-20", 10), Some(Some(0..=9)));
+        assert_eq!(parse_range("bytes=-0", 10), Some(None));
+    }
+
+    #[test]
+    fn start_past_the_end() {
+        assert_eq!(parse_range("bytes=10-", 10), Some(None));
+        assert_eq!(parse_range("bytes=20-30", 10), Some(None));
+    }
+
+    #[test]
+    fn end_before_start() {
+        assert_eq!(parse_range("bytes=3-1", 10), None);
+    }
+
+    #[test]
+    fn empty_file() {
+        assert_eq!(parse_range("bytes=0-", 0), Some(None));
+        assert_eq!(parse_range("bytes=0-9", 0), Some(None));
+        assert_eq!(parse_range("bytes=-5", 0), Some(None));
+    }
+
+    #[test]
+    fn ignored_headers() {
+        assert_eq!(parse_range("bytes=1-2,4-5", 10), None);
+        assert_eq!(parse_range("bytes=-", 10), None);
+        assert_eq!(parse_range("items=0-1", 10), None);
+        assert_eq!(parse_range("bytes=a-b", 10), None);
+    }
+}
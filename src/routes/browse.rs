@@ -1,10 +1,13 @@
+use crate::formats::text::{self, TEXT_EXT};
 use crate::index::collector::CollectAll;
 use crate::index::state::{EntryType, Fields, IndexState};
+use crate::routes::file::{doc_hash, read};
 use crate::AppState;
 use axum::extract::{Query, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
+use futures_util::future::join_all;
 use serde::{Deserialize, Serialize};
 use std::ops::Bound;
 use std::path::PathBuf;
@@ -12,15 +15,9 @@ use tantivy::collector::TopDocs;
 use tantivy::query::{BooleanQuery, FuzzyTermQuery, Occur, RangeQuery, TermQuery};
 use tantivy::schema::IndexRecordOption::Basic;
 use tantivy::schema::Value;
+use tantivy::snippet::SnippetGenerator;
 use tantivy::{Searcher, TantivyDocument, Term};
 
-pub const TEXT_EXT: &[&str] = &[
-    "act", "amd", "ao", "aoc", "arm", "atl", "atlas", "cht", "clt", "csd", "dct", "ddt", "dgr",
-    "dlp", "ecf", "env", "epk", "et", "ffx", "fgp", "filter", "fxgraph", "gft", "gt", "h",
-    "hideout", "hlsl", "inc", "it", "itc", "mat", "mtd", "ot", "otc", "pet", "rs", "slg", "sm",
-    "tgr", "tgt", "tmo", "toy", "trl", "tsi", "tst", "txt", "ui", "xml",
-];
-
 #[derive(Deserialize, Default, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
 enum Command {
@@ -44,12 +41,25 @@ pub struct Params {
     filter: String,
     #[serde(default)]
     extension: String,
+    #[serde(default)]
+    #[serde(rename = "type")]
+    node_type: String,
     limit: Option<usize>,
     #[serde(default)]
     debug_query: bool,
     #[serde(default)]
     deep: String,
     size: Option<String>,
+    width: Option<String>,
+    height: Option<String>,
+    mips: Option<String>,
+    #[serde(default)]
+    format: String,
+    vertices: Option<String>,
+    triangles: Option<String>,
+    /// Content hash in hex, to find every copy of a file.
+    #[serde(default)]
+    hash: String,
 }
 
 #[derive(Serialize)]
@@ -81,6 +91,21 @@ pub struct Node {
     pub bundle_offset: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sprite: Option<Sprite>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub texture: Option<Texture>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<Mesh>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio: Option<Audio>,
+    /// Hash of the file's contents in hex, equal for identical files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Number of entries beneath a file, which can then be expanded like a folder.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<u64>,
+    /// Matching excerpt of the file contents, with matches highlighted in `<b>` tags.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -99,11 +124,41 @@ pub struct Sprite {
     pub h: u64,
 }
 
+#[derive(Serialize)]
+pub struct Texture {
+    pub width: u64,
+    pub height: u64,
+    pub mips: u64,
+    pub format: String,
+}
+
+#[derive(Serialize)]
+pub struct Mesh {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vertices: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub triangles: Option<u64>,
+    pub bbox: Vec<f64>,
+    pub materials: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct Audio {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Length in seconds.
+    pub duration: f64,
+    pub channels: u64,
+    pub sample_rate: u64,
+}
+
 #[derive(Serialize, Eq, PartialEq, Ord, PartialOrd)]
 #[serde(rename_all = "lowercase")]
 pub enum NodeType {
     Dir,
     File,
+    Sprite,
+    Sound,
 }
 
 #[derive(Serialize)]
@@ -114,40 +169,33 @@ pub struct ErrorResponse<'a> {
 
 const MB: u64 = 1000000;
 
+/// Content searches only read back this many hits to build snippets from.
+const MAX_SNIPPETS: usize = 20;
+
 pub async fn handler(
     Query(Params {
         adapter,
         command,
         filter,
         extension,
-        mut path,
+        node_type,
+        path,
         mut limit,
         debug_query,
         deep,
         size,
+        width,
+        height,
+        mips,
+        format,
+        vertices,
+        triangles,
+        hash,
     }): Query<Params>,
     State(state): State<AppState>,
 ) -> Result<Json<IndexResponse>, Response> {
     let storages = state.storages().await;
-
-    let mut adapter = adapter;
-    if adapter.is_none() && !path.is_empty() {
-        if let Some((first, rest)) = path.split_once('/') {
-            if storages.contains(&first.to_string()) {
-                adapter = Some(first.to_string());
-                path = rest.to_string();
-            }
-        } else if storages.contains(&path) {
-            adapter = Some(path.to_string());
-            path = String::new();
-        }
-    }
-
-    let (adapter, urls) = match adapter {
-        Some(a) if storages.contains(&a) => (a.clone(), state.urls(&a).await),
-        Some(a) => (a.clone(), vec![a]),
-        None => (storages[0].clone(), state.urls(&storages[0]).await),
-    };
+    let (adapter, urls, mut path) = resolve_storage(&state, &storages, adapter, path).await;
 
     if limit == Some(0) {
         limit = None
@@ -172,20 +220,16 @@ pub async fn handler(
         reader,
         fields,
         query_parser,
+        content_parser,
         ..
     } = state.index;
 
+    let mut content_query: Option<Box<dyn tantivy::query::Query>> = None;
+
     let mut query: Vec<(Occur, Box<dyn tantivy::query::Query>)> = Vec::with_capacity(4);
 
-    let mut version_query: Vec<(Occur, Box<dyn tantivy::query::Query>)> = Vec::new();
-    for url in &urls {
-        version_query.push((
-            Occur::Should,
-            Box::new(TermQuery::new(fields.version_term(url.as_str()), Basic)),
-        ));
-    }
-    if !version_query.is_empty() {
-        query.push((Occur::Must, Box::new(BooleanQuery::new(version_query))));
+    if let Some(version_query) = version_query(fields, &urls) {
+        query.push((Occur::Must, version_query));
     }
 
     if !extension.is_empty() {
@@ -214,6 +258,15 @@ pub async fn handler(
             )),
         ))
     } else if command == Command::Search {
+        if !node_type.is_empty() {
+            query.push((
+                Occur::Must,
+                Box::new(TermQuery::new(
+                    Term::from_field_text(fields.typ, node_type.as_str()),
+                    Basic,
+                )),
+            ))
+        }
         if let Some(range) = match size.as_deref() {
             Some("small") => Some((0u64, MB)),
             Some("medium") => Some((MB, 10 * MB)),
@@ -228,6 +281,45 @@ pub async fn handler(
                 )),
             ))
         }
+        for (field, range) in [
+            (fields.texture_width, width),
+            (fields.texture_height, height),
+            (fields.texture_mips, mips),
+            (fields.mesh_vertices, vertices),
+            (fields.mesh_triangles, triangles),
+        ] {
+            if let Some(range) = range.filter(|r| !r.is_empty()) {
+                let (from, to) = parse_range(&range)
+                    .ok_or_else(|| error(format!("invalid range {range}"), &storages))?;
+                query.push((
+                    Occur::Must,
+                    Box::new(RangeQuery::new(
+                        from.map(|v| Term::from_field_u64(field, v)),
+                        to.map(|v| Term::from_field_u64(field, v)),
+                    )),
+                ))
+            }
+        }
+        if !hash.is_empty() {
+            let hash = u64::from_str_radix(&hash, 16)
+                .map_err(|_| error(format!("invalid hash {hash}"), &storages))?;
+            query.push((
+                Occur::Must,
+                Box::new(TermQuery::new(
+                    Term::from_field_u64(fields.content_hash, hash),
+                    Basic,
+                )),
+            ))
+        }
+        if !format.is_empty() {
+            query.push((
+                Occur::Must,
+                Box::new(TermQuery::new(
+                    Term::from_field_text(fields.texture_format, &format.to_lowercase()),
+                    Basic,
+                )),
+            ))
+        }
         if deep == "1" {
             if !path.is_empty() {
                 query.push((
@@ -248,7 +340,13 @@ pub async fn handler(
                 )),
             ))
         }
-        if !filter.is_empty() {
+        if let Some(text) = filter.strip_prefix("content:") {
+            let parsed = content_parser
+                .parse_query(text)
+                .map_err(|e| error(format!("error performing query: {e}"), &storages))?;
+            content_query = Some(parsed.box_clone());
+            query.push((Occur::Must, parsed))
+        } else if !filter.is_empty() {
             query.push((
                 Occur::Must,
                 query_parser
@@ -266,29 +364,57 @@ pub async fn handler(
         ))
     }
     if command == Command::Subfolders {
+        // files with entries beneath them expand like folders
         query.push((
             Occur::Must,
-            Box::new(TermQuery::new(
-                Term::from_field_text(fields.typ, EntryType::DIR),
-                Basic,
-            )),
+            Box::new(BooleanQuery::new(vec![
+                (
+                    Occur::Should,
+                    Box::new(TermQuery::new(
+                        Term::from_field_text(fields.typ, EntryType::DIR),
+                        Basic,
+                    )),
+                ),
+                (
+                    Occur::Should,
+                    Box::new(RangeQuery::new(
+                        Bound::Included(Term::from_field_u64(fields.children, 1)),
+                        Bound::Unbounded,
+                    )),
+                ),
+            ])),
         ))
     }
     let query: Box<dyn tantivy::query::Query> = Box::new(BooleanQuery::new(query));
 
     let debug_query = debug_query.then(|| format!("{query:?}"));
 
-    let mut files = perform_query(&reader.searcher(), &storages, query, limit, |doc| {
-        process_doc(adapter.clone(), fields, doc)
+    let searcher = reader.searcher();
+    let snippets = content_query
+        .map(|q| SnippetGenerator::create(&searcher, &*q, fields.content))
+        .transpose()
+        .map_err(|e| error(format!("error performing query: {e}"), &storages))?;
+
+    let mut hits = Vec::new();
+    let mut files = perform_query(&searcher, &storages, query, limit, |doc| {
+        let doc = doc?;
+        if snippets.is_some() && hits.len() < MAX_SNIPPETS {
+            hits.push(doc.clone());
+        }
+        process_doc(adapter.clone(), fields, Ok(doc))
     })?;
+    // contents are not stored in the index, so the top hits are read back from their bundles
+    if let Some(snippets) = snippets {
+        let texts = join_all(hits.iter().map(|doc| read(&state, doc))).await;
+        for (node, data) in files.iter_mut().zip(texts) {
+            node.snippet = data
+                .ok()
+                .map(|data| snippets.snippet(&text::decode(&data)).to_html());
+        }
+    }
 
     if limit.is_none() {
-        files.sort_by(|l, r| {
-            l.node_type
-                .cmp(&r.node_type)
-                .then(l.path.cmp(&r.path))
-                .then(l.basename.cmp(&r.basename))
-        });
+        sort_nodes(&mut files);
     }
 
     Ok(Json(IndexResponse {
@@ -299,7 +425,81 @@ pub async fn handler(
     }))
 }
 
-fn process_doc(
+/// Parses `n`, `min..max`, `min..` or `..max` into inclusive bounds.
+fn parse_range(range: &str) -> Option<(Bound<u64>, Bound<u64>)> {
+    let bound = |v: &str| -> Option<Bound<u64>> {
+        if v.is_empty() {
+            Some(Bound::Unbounded)
+        } else {
+            v.parse().ok().map(Bound::Included)
+        }
+    };
+    match range.split_once("..") {
+        Some((from, to)) => Some((bound(from)?, bound(to)?)),
+        None => {
+            let v = range.parse().ok()?;
+            Some((Bound::Included(v), Bound::Included(v)))
+        }
+    }
+}
+
+/// Works out which storage a request is for, either from `adapter` or from the first segment of
+/// `path`, returning the storage name, the versions to search and the remaining path.
+pub(crate) async fn resolve_storage(
+    state: &AppState,
+    storages: &[String],
+    adapter: Option<String>,
+    mut path: String,
+) -> (String, Vec<String>, String) {
+    let mut adapter = adapter;
+    if adapter.is_none() && !path.is_empty() {
+        if let Some((first, rest)) = path.split_once('/') {
+            if storages.contains(&first.to_string()) {
+                adapter = Some(first.to_string());
+                path = rest.to_string();
+            }
+        } else if storages.contains(&path) {
+            adapter = Some(path.to_string());
+            path = String::new();
+        }
+    }
+
+    let (adapter, urls) = match adapter {
+        Some(a) if storages.contains(&a) => (a.clone(), state.urls(&a).await),
+        Some(a) => (a.clone(), vec![a]),
+        None => (storages[0].clone(), state.urls(&storages[0]).await),
+    };
+    (adapter, urls, path)
+}
+
+pub(crate) fn version_query(
+    fields: &Fields,
+    urls: &[String],
+) -> Option<Box<dyn tantivy::query::Query>> {
+    let mut version_query: Vec<(Occur, Box<dyn tantivy::query::Query>)> = Vec::new();
+    for url in urls {
+        version_query.push((
+            Occur::Should,
+            Box::new(TermQuery::new(fields.version_term(url.as_str()), Basic)),
+        ));
+    }
+    if version_query.is_empty() {
+        None
+    } else {
+        Some(Box::new(BooleanQuery::new(version_query)))
+    }
+}
+
+pub(crate) fn sort_nodes(files: &mut [Node]) {
+    files.sort_by(|l, r| {
+        l.node_type
+            .cmp(&r.node_type)
+            .then(l.path.cmp(&r.path))
+            .then(l.basename.cmp(&r.basename))
+    });
+}
+
+pub(crate) fn process_doc(
     storage: String,
     fields: &Fields,
     doc: Result<TantivyDocument, Response>,
@@ -315,30 +515,35 @@ fn process_doc(
         .and_then(|v| v.as_str())
         .unwrap_or_default()
         .to_string();
-    let node_type = if doc.get_first(fields.typ).and_then(|v| v.as_str()) == Some(EntryType::DIR) {
-        NodeType::Dir
-    } else {
-        NodeType::File
+    let node_type = match doc.get_first(fields.typ).and_then(|v| v.as_str()) {
+        Some(EntryType::DIR) => NodeType::Dir,
+        Some(EntryType::SPRITE) => NodeType::Sprite,
+        Some(EntryType::SOUND) => NodeType::Sound,
+        _ => NodeType::File,
     };
     let file_size = doc.get_first(fields.size).and_then(|v| v.as_u64());
     let bundle_offset = doc.get_first(fields.offset).and_then(|v| v.as_u64());
     let path: PathBuf = [&dirname, &basename].iter().collect();
-    let extension = if node_type == NodeType::Dir {
-        None
-    } else {
+    let extension = if node_type == NodeType::File {
         path.extension().map(|v| v.to_string_lossy().to_string())
+    } else {
+        None
     };
     let path = path.to_string_lossy().to_string();
-    let mime_type = extension.as_ref().and_then(|ext| {
-        mime_guess::from_ext(ext)
-            .first()
-            .map(|m| m.to_string())
-            .or_else(|| {
-                TEXT_EXT
-                    .contains(&ext.as_str())
-                    .then_some("text/plain".to_string())
-            })
-    });
+    let mime_type = if node_type == NodeType::Sprite {
+        Some("image/png".to_string())
+    } else {
+        extension.as_ref().and_then(|ext| {
+            mime_guess::from_ext(ext)
+                .first()
+                .map(|m| m.to_string())
+                .or_else(|| {
+                    TEXT_EXT
+                        .contains(&ext.as_str())
+                        .then_some("text/plain".to_string())
+                })
+        })
+    };
 
     let bundle = doc
         .get_first(fields.bundle)
@@ -375,6 +580,63 @@ fn process_doc(
         None
     };
 
+    let texture = if let (Some(width), Some(height), Some(mips), Some(format)) = (
+        doc.get_first(fields.texture_width).and_then(|v| v.as_u64()),
+        doc.get_first(fields.texture_height)
+            .and_then(|v| v.as_u64()),
+        doc.get_first(fields.texture_mips).and_then(|v| v.as_u64()),
+        doc.get_first(fields.texture_format)
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string()),
+    ) {
+        Some(Texture {
+            width,
+            height,
+            mips,
+            format,
+        })
+    } else {
+        None
+    };
+
+    let bbox = doc
+        .get_all(fields.mesh_bbox)
+        .filter_map(|v| v.as_f64())
+        .collect::<Vec<_>>();
+    let mesh = (!bbox.is_empty()).then(|| Mesh {
+        vertices: doc.get_first(fields.mesh_vertices).and_then(|v| v.as_u64()),
+        triangles: doc
+            .get_first(fields.mesh_triangles)
+            .and_then(|v| v.as_u64()),
+        bbox,
+        materials: doc
+            .get_all(fields.mesh_materials)
+            .filter_map(|v| v.as_str())
+            .map(|v| v.to_string())
+            .collect(),
+    });
+
+    let audio = if let (Some(duration), Some(channels), Some(sample_rate)) = (
+        doc.get_first(fields.audio_duration)
+            .and_then(|v| v.as_f64()),
+        doc.get_first(fields.audio_channels)
+            .and_then(|v| v.as_u64()),
+        doc.get_first(fields.audio_sample_rate)
+            .and_then(|v| v.as_u64()),
+    ) {
+        Some(Audio {
+            format: doc
+                .get_first(fields.audio_format)
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_string()),
+            duration,
+            channels,
+            sample_rate,
+        })
+    } else {
+        None
+    };
+
     Ok(Node {
         path,
         dirname,
@@ -387,10 +649,19 @@ fn process_doc(
         bundle_offset,
         bundle,
         sprite,
+        texture,
+        mesh,
+        audio,
+        children: doc.get_first(fields.children).and_then(|v| v.as_u64()),
+        content_hash: doc_hash(fields, &doc).map(|hash| format!("{hash:016x}")),
+        snippet: None,
     })
 }
 
-fn perform_query<T, M: FnMut(Result<TantivyDocument, Response>) -> Result<T, Response>>(
+pub(crate) fn perform_query<
+    T,
+    M: FnMut(Result<TantivyDocument, Response>) -> Result<T, Response>,
+>(
     searcher: &Searcher,
     storages: &[String],
     query: Box<dyn tantivy::query::Query>,
@@ -417,7 +688,7 @@ fn perform_query<T, M: FnMut(Result<TantivyDocument, Response>) -> Result<T, Res
     results
 }
 
-fn error(error: String, storages: &[String]) -> Response {
+pub(crate) fn error(error: String, storages: &[String]) -> Response {
     let mut resp = Json(ErrorResponse { error, storages }).into_response();
     *resp.status_mut() = StatusCode::NOT_FOUND;
     resp
@@ -0,0 +1,755 @@
+use crate::formats::dat::{Dat, Schema, Table};
+use crate::index::dat_strings::DatStringsState;
+use crate::index::state::{EntryType, IndexState};
+use crate::routes::browse::resolve_storage;
+use crate::routes::file::{doc_hash, doc_path, doc_version, failure, find, lookup, read};
+use crate::AppState;
+use axum::body::{Body, Bytes};
+use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::ops::Range;
+use tantivy::collector::TopDocs;
+use tantivy::query::{BooleanQuery, Occur, TermQuery};
+use tantivy::schema::{IndexRecordOption, Value as _};
+use tantivy::TantivyDocument;
+use tokio::sync::mpsc;
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+/// Rows encoded per chunk of an export.
+const EXPORT_CHUNK: usize = 512;
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Json,
+    Csv,
+    Ndjson,
+}
+
+#[derive(Deserialize)]
+pub struct Params {
+    adapter: Option<String>,
+    #[serde(default)]
+    path: String,
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+    /// Comma-separated column names to return instead of every column.
+    columns: Option<String>,
+    /// `csv` or `ndjson` export every row, unless `limit` is given.
+    #[serde(default)]
+    format: Format,
+    /// Comma-separated row reference columns to replace with the rows they point at.
+    expand: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DatResponse {
+    table: String,
+    row_count: usize,
+    row_width: usize,
+    schema_width: usize,
+    offset: usize,
+    columns: Vec<String>,
+    rows: Vec<Vec<Value>>,
+}
+
+/// Returns a page of rows of a dat table, decoded with the configured schema.
+pub async fn handler(
+    Query(Params {
+        adapter,
+        path,
+        offset,
+        limit,
+        columns,
+        format,
+        expand,
+    }): Query<Params>,
+    State(state): State<AppState>,
+) -> Result<Response, Response> {
+    let IndexState { fields, .. } = state.index;
+    let doc = lookup(&state, adapter, path, EntryType::FILE).await?;
+    let version = doc_version(fields, &doc)?;
+    let path = doc_path(fields, &doc);
+    let storage = state.storage_of(version).await;
+    let table = schema_table(&state, &path, storage.as_deref())?;
+    let data = read(&state, &doc).await?;
+    let dat = Dat::parse(&data).map_err(|e| failure(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    let selected = select_columns(table, columns.as_deref(), dat.row_width)?;
+
+    if format != Format::Json {
+        let end = limit.map_or(dat.row_count, |limit| {
+            dat.row_count.min(offset.saturating_add(limit))
+        });
+        return Ok(export(format, table.clone(), selected, data, offset..end));
+    }
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let names = table.column_names();
+    let offsets = table.offsets();
+    let mut rows = (offset..dat.row_count.min(offset.saturating_add(limit)))
+        .map(|row| dat.read_columns(row, table, &selected, &offsets))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map_err(|e| failure(StatusCode::UNPROCESSABLE_ENTITY, format!("{e:?}")))?;
+    for column in expand.iter().flat_map(|e| e.split(',')) {
+        let i = select_columns(table, Some(column), usize::MAX)?[0];
+        let position = selected.iter().position(|&s| s == i).ok_or_else(|| {
+            failure(
+                StatusCode::BAD_REQUEST,
+                format!("cannot expand {column} without selecting it"),
+            )
+        })?;
+        let target = table.columns[i]
+            .referenced_table(&table.name)
+            .ok_or_else(|| {
+                failure(
+                    StatusCode::BAD_REQUEST,
+                    format!("{column} does not reference rows"),
+                )
+            })?;
+        let target = schema(&state)?
+            .table(target, storage.as_deref())
+            .ok_or_else(|| {
+                failure(
+                    StatusCode::NOT_FOUND,
+                    format!("no schema for table {target}"),
+                )
+            })?;
+        let target_path = sibling_path(&path, &target.name);
+        let target_data = read_in(&state, version, &target_path).await?;
+        let target = target.clone();
+        rows = tokio::task::spawn_blocking(move || {
+            let target_dat = Dat::parse(&target_data)?;
+            rows.into_iter()
+                .map(|mut row| {
+                    let value = std::mem::take(&mut row[position]);
+                    row[position] = expand_value(value, &target, &target_dat)?;
+                    Ok(row)
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        })
+        .await
+        .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .map_err(|e| failure(StatusCode::UNPROCESSABLE_ENTITY, format!("{e:?}")))?;
+    }
+
+    Ok(Json(DatResponse {
+        table: table.name.clone(),
+        row_count: dat.row_count,
+        row_width: dat.row_width,
+        schema_width: table.width(),
+        offset,
+        columns: selected.iter().map(|&i| names[i].clone()).collect(),
+        rows,
+    })
+    .into_response())
+}
+
+#[derive(Deserialize)]
+pub struct DiffParams {
+    #[serde(default)]
+    path: String,
+    /// Version URL of the old table.
+    from: String,
+    /// Version URL of the new table.
+    to: String,
+    /// Column to match rows by instead of the schema's key column.
+    key: Option<String>,
+    columns: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DiffResponse {
+    table: String,
+    /// Column rows were matched by, or `None` if they were matched by index.
+    key: Option<String>,
+    columns: Vec<String>,
+    added: Vec<DiffRow>,
+    removed: Vec<DiffRow>,
+    modified: Vec<ModifiedRow>,
+}
+
+impl DiffResponse {
+    /// A diff of the `selected` columns with no changes.
+    fn unchanged(table: &Table, key: Option<usize>, selected: &[usize]) -> Self {
+        let names = table.column_names();
+        Self {
+            table: table.name.clone(),
+            key: key.map(|i| names[i].clone()),
+            columns: selected.iter().map(|&i| names[i].clone()).collect(),
+            added: Vec::new(),
+            removed: Vec::new(),
+            modified: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct DiffRow {
+    key: Value,
+    index: usize,
+    values: Vec<Value>,
+}
+
+#[derive(Serialize)]
+struct ModifiedRow {
+    key: Value,
+    from_index: usize,
+    to_index: usize,
+    changes: Vec<Change>,
+}
+
+#[derive(Serialize)]
+struct Change {
+    column: String,
+    before: Value,
+    after: Value,
+}
+
+/// Compares a dat table between two versions row by row.
+pub async fn diff_handler(
+    Query(DiffParams {
+        path,
+        from,
+        to,
+        key,
+        columns,
+    }): Query<DiffParams>,
+    State(state): State<AppState>,
+) -> Result<Response, Response> {
+    let IndexState { reader, fields, .. } = state.index;
+    let path = path.trim_start_matches('/').to_lowercase();
+    let searcher = reader.searcher();
+    let find_in = |version: &str| {
+        find(
+            &searcher,
+            fields,
+            &[version.to_string()],
+            &path,
+            EntryType::FILE,
+        )
+        .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(|| {
+            failure(
+                StatusCode::NOT_FOUND,
+                format!("{path} not found in {version}"),
+            )
+        })
+    };
+    let (from_doc, to_doc) = (find_in(&from)?, find_in(&to)?);
+    let storage = state.storage_of(&to).await;
+    let table = schema_table(&state, &path, storage.as_deref())?.clone();
+    let key = match key {
+        Some(key) => Some(select_columns(&table, Some(&key), usize::MAX)?[0]),
+        None => table.key_column(),
+    };
+    // files with the same contents cannot differ, so neither side needs downloading
+    if doc_hash(fields, &from_doc).is_some_and(|h| doc_hash(fields, &to_doc) == Some(h)) {
+        let selected = select_columns(&table, columns.as_deref(), table.width())?;
+        return Ok(Json(DiffResponse::unchanged(&table, key, &selected)).into_response());
+    }
+    let (before, after) = tokio::try_join!(read(&state, &from_doc), read(&state, &to_doc))?;
+    let response = tokio::task::spawn_blocking(move || {
+        let before = Dat::parse(&before)?;
+        let after = Dat::parse(&after)?;
+        diff(&table, &before, &after, key, columns.as_deref())
+    })
+    .await
+    .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?
+    .map_err(|e| failure(StatusCode::UNPROCESSABLE_ENTITY, format!("{e:?}")))?;
+    Ok(Json(response).into_response())
+}
+
+fn diff(
+    table: &Table,
+    before: &Dat,
+    after: &Dat,
+    key: Option<usize>,
+    columns: Option<&str>,
+) -> anyhow::Result<DiffResponse> {
+    let row_width = before.row_width.min(after.row_width);
+    let selected = select_columns(table, columns, row_width)
+        .map_err(|_| anyhow::anyhow!("unknown column in {columns:?}"))?;
+    let names = table.column_names();
+    let offsets = table.offsets();
+    let keys = |dat: &Dat| -> anyhow::Result<Vec<String>> {
+        let mut seen = HashMap::new();
+        (0..dat.row_count)
+            .map(|row| {
+                let key = match key {
+                    Some(i) => dat.read(row, &table.columns[i], offsets[i])?.to_string(),
+                    None => row.to_string(),
+                };
+                // Keys the schema calls unique sometimes repeat, so number the repeats.
+                let n = seen.entry(key.clone()).or_insert(0);
+                *n += 1;
+                Ok(format!("{key}#{n}"))
+            })
+            .collect()
+    };
+    let key_value = |dat: &Dat, row: usize| match key {
+        Some(i) => dat.read(row, &table.columns[i], offsets[i]),
+        None => Ok(Value::from(row)),
+    };
+    let row = |dat: &Dat, index: usize| -> anyhow::Result<DiffRow> {
+        Ok(DiffRow {
+            key: key_value(dat, index)?,
+            index,
+            values: dat.read_columns(index, table, &selected, &offsets)?,
+        })
+    };
+
+    let before_keys = keys(before)?;
+    let after_keys = keys(after)?;
+    let after_rows = after_keys
+        .iter()
+        .enumerate()
+        .map(|(i, k)| (k, i))
+        .collect::<HashMap<_, _>>();
+    let before_rows = before_keys
+        .iter()
+        .enumerate()
+        .map(|(i, k)| (k, i))
+        .collect::<HashMap<_, _>>();
+
+    let mut removed = Vec::new();
+    let mut modified = Vec::new();
+    for (from_index, k) in before_keys.iter().enumerate() {
+        let Some(&to_index) = after_rows.get(k) else {
+            removed.push(row(before, from_index)?);
+            continue;
+        };
+        let old = before.read_columns(from_index, table, &selected, &offsets)?;
+        let new = after.read_columns(to_index, table, &selected, &offsets)?;
+        let changes = selected
+            .iter()
+            .zip(old.into_iter().zip(new))
+            .filter(|(_, (old, new))| old != new)
+            .map(|(&i, (before, after))| Change {
+                column: names[i].clone(),
+                before,
+                after,
+            })
+            .collect::<Vec<_>>();
+        if !changes.is_empty() {
+            modified.push(ModifiedRow {
+                key: key_value(after, to_index)?,
+                from_index,
+                to_index,
+                changes,
+            });
+        }
+    }
+    let added = after_keys
+        .iter()
+        .enumerate()
+        .filter(|(_, k)| !before_rows.contains_key(k))
+        .map(|(to_index, _)| row(after, to_index))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(DiffResponse {
+        added,
+        removed,
+        modified,
+        ..DiffResponse::unchanged(table, key, &selected)
+    })
+}
+
+#[derive(Deserialize)]
+pub struct SearchParams {
+    adapter: Option<String>,
+    #[serde(default)]
+    q: String,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct SearchResult {
+    version: String,
+    path: String,
+    table: String,
+    row: u64,
+    column: String,
+    value: String,
+}
+
+/// Finds dat rows with a string column matching the query.
+pub async fn search_handler(
+    Query(SearchParams { adapter, q, limit }): Query<SearchParams>,
+    State(state): State<AppState>,
+) -> Result<Response, Response> {
+    let DatStringsState {
+        reader,
+        fields,
+        query_parser,
+        ..
+    } = state.dat_strings;
+    let storages = state.storages().await;
+    let (_, urls, _) = resolve_storage(&state, &storages, adapter, String::new()).await;
+    let query = query_parser
+        .parse_query(&q)
+        .map_err(|e| failure(StatusCode::BAD_REQUEST, format!("invalid query: {e}")))?;
+    let versions = urls
+        .iter()
+        .map(|url| -> (Occur, Box<dyn tantivy::query::Query>) {
+            let term = TermQuery::new(fields.version_term(url), IndexRecordOption::Basic);
+            (Occur::Should, Box::new(term))
+        })
+        .collect::<Vec<_>>();
+    let query = BooleanQuery::new(vec![
+        (Occur::Must, query),
+        (Occur::Must, Box::new(BooleanQuery::new(versions))),
+    ]);
+
+    let searcher = reader.searcher();
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let found = searcher
+        .search(&query, &TopDocs::with_limit(limit).order_by_score())
+        .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let results = found
+        .into_iter()
+        .map(|(_, addr)| {
+            let doc = searcher.doc::<TantivyDocument>(addr)?;
+            let text = |field| {
+                doc.get_first(field)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            Ok(SearchResult {
+                version: text(fields.version),
+                path: text(fields.path),
+                table: text(fields.table),
+                row: doc
+                    .get_first(fields.row)
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or_default(),
+                column: text(fields.column),
+                value: text(fields.value),
+            })
+        })
+        .collect::<tantivy::Result<Vec<_>>>()
+        .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(results).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct ReferencesParams {
+    adapter: Option<String>,
+    #[serde(default)]
+    path: String,
+    row: u64,
+}
+
+#[derive(Serialize)]
+struct Referrer {
+    table: String,
+    path: String,
+    column: String,
+    row: usize,
+}
+
+/// Lists the rows of other tables whose row reference columns point at a row.
+pub async fn references_handler(
+    Query(ReferencesParams { adapter, path, row }): Query<ReferencesParams>,
+    State(state): State<AppState>,
+) -> Result<Response, Response> {
+    let IndexState { reader, fields, .. } = state.index;
+    let doc = lookup(&state, adapter, path, EntryType::FILE).await?;
+    let version = doc_version(fields, &doc)?;
+    let path = doc_path(fields, &doc);
+    let storage = state.storage_of(version).await;
+    let table = schema_table(&state, &path, storage.as_deref())?;
+    let schema = schema(&state)?;
+
+    let searcher = reader.searcher();
+    let mut referrers = Vec::new();
+    for referrer in schema.tables_for(storage.as_deref()) {
+        let columns = referrer
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| {
+                c.referenced_table(&referrer.name)
+                    .is_some_and(|t| t.eq_ignore_ascii_case(&table.name))
+            })
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        if columns.is_empty() {
+            continue;
+        }
+        let referrer_path = sibling_path(&path, &referrer.name);
+        let found = find(
+            &searcher,
+            fields,
+            &[version.to_string()],
+            &referrer_path,
+            EntryType::FILE,
+        )
+        .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        if let Some(doc) = found {
+            referrers.push((referrer, referrer_path, columns, doc));
+        }
+    }
+    let mut results = Vec::new();
+    for (referrer, referrer_path, columns, doc) in referrers {
+        let data = read(&state, &doc).await?;
+        let table = referrer.clone();
+        let found =
+            tokio::task::spawn_blocking(move || referencing_rows(&table, &data, &columns, row))
+                .await
+                .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?
+                .map_err(|e| failure(StatusCode::UNPROCESSABLE_ENTITY, format!("{e:?}")))?;
+        results.extend(found.into_iter().map(|(column, row)| Referrer {
+            table: referrer.name.clone(),
+            path: referrer_path.clone(),
+            column,
+            row,
+        }));
+    }
+    Ok(Json(results).into_response())
+}
+
+/// Rows of `table` with one of `columns` pointing at `target`, as column name and row index.
+fn referencing_rows(
+    table: &Table,
+    data: &[u8],
+    columns: &[usize],
+    target: u64,
+) -> anyhow::Result<Vec<(String, usize)>> {
+    let dat = Dat::parse(data)?;
+    let names = table.column_names();
+    let offsets = table.offsets();
+    let columns = columns
+        .iter()
+        .filter(|&&i| offsets[i] + table.columns[i].width() <= dat.row_width)
+        .collect::<Vec<_>>();
+    let mut found = Vec::new();
+    for row in 0..dat.row_count {
+        for &&i in &columns {
+            let matches = match dat.read(row, &table.columns[i], offsets[i])? {
+                Value::Array(values) => values.iter().any(|v| v.as_u64() == Some(target)),
+                value => value.as_u64() == Some(target),
+            };
+            if matches {
+                found.push((names[i].clone(), row));
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Replaces row indexes, or arrays of them, with the rows they point at.
+fn expand_value(value: Value, table: &Table, dat: &Dat) -> anyhow::Result<Value> {
+    match value {
+        Value::Array(values) => values
+            .into_iter()
+            .map(|v| expand_value(v, table, dat))
+            .collect(),
+        Value::Number(n) => match n.as_u64() {
+            Some(row) if (row as usize) < dat.row_count => {
+                let row = row as usize;
+                let selected = select_columns(table, None, dat.row_width)
+                    .map_err(|_| anyhow::anyhow!("selecting columns of {}", table.name))?;
+                let names = table.column_names();
+                let values = dat.read_columns(row, table, &selected, &table.offsets())?;
+                let mut object = serde_json::Map::new();
+                object.insert("_row".to_string(), Value::from(row));
+                for (i, value) in selected.into_iter().zip(values) {
+                    object.insert(names[i].clone(), value);
+                }
+                Ok(Value::Object(object))
+            }
+            _ => Ok(Value::Number(n)),
+        },
+        other => Ok(other),
+    }
+}
+
+/// Tables reference each other by name, and their files live side by side.
+fn sibling_path(path: &str, table: &str) -> String {
+    let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+    format!("{parent}/{}.datc64", table.to_lowercase())
+}
+
+/// Reads the file at `path` in exactly `version`.
+async fn read_in(state: &AppState, version: &str, path: &str) -> Result<Vec<u8>, Response> {
+    let IndexState { reader, fields, .. } = state.index;
+    let doc = find(
+        &reader.searcher(),
+        fields,
+        &[version.to_string()],
+        path,
+        EntryType::FILE,
+    )
+    .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?
+    .ok_or_else(|| failure(StatusCode::NOT_FOUND, format!("{path} not found")))?;
+    read(state, &doc).await
+}
+
+fn schema(state: &AppState) -> Result<&Schema, Response> {
+    state.schema.as_deref().ok_or_else(|| {
+        failure(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "no dat schema configured, set DAT_SCHEMA",
+        )
+    })
+}
+
+/// Finds the schema definition of the dat file at `path`.
+pub(crate) fn schema_table<'a>(
+    state: &'a AppState,
+    path: &str,
+    storage: Option<&str>,
+) -> Result<&'a Table, Response> {
+    let schema = schema(state)?;
+    let name = Schema::table_name(path).ok_or_else(|| {
+        failure(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "file is not a dat table",
+        )
+    })?;
+    schema
+        .table(name, storage)
+        .ok_or_else(|| failure(StatusCode::NOT_FOUND, format!("no schema for table {name}")))
+}
+
+/// Resolves comma-separated column names to column indexes, defaulting to every column that fits
+/// the rows.
+pub(crate) fn select_columns(
+    table: &Table,
+    columns: Option<&str>,
+    row_width: usize,
+) -> Result<Vec<usize>, Response> {
+    let names = table.column_names();
+    let offsets = table.offsets();
+    match columns {
+        Some(columns) => columns
+            .split(',')
+            .map(|c| {
+                names
+                    .iter()
+                    .position(|n| n.eq_ignore_ascii_case(c.trim()))
+                    .ok_or_else(|| failure(StatusCode::BAD_REQUEST, format!("unknown column {c}")))
+            })
+            .collect(),
+        None => Ok((0..names.len())
+            .filter(|&i| offsets[i] + table.columns[i].width() <= row_width)
+            .collect()),
+    }
+}
+
+/// Streams rows as CSV or NDJSON while a blocking task encodes them in chunks.
+fn export(
+    format: Format,
+    table: Table,
+    selected: Vec<usize>,
+    data: Vec<u8>,
+    rows: Range<usize>,
+) -> Response {
+    let (content_type, extension) = match format {
+        Format::Csv => ("text/csv; charset=utf-8", "csv"),
+        _ => ("application/x-ndjson", "ndjson"),
+    };
+    let disposition = format!("attachment; filename=\"{}.{extension}\"", table.name);
+    let (tx, rx) = mpsc::channel(4);
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = encode_rows(format, &table, &selected, &data, rows, &tx) {
+            eprintln!("Failed to export {}: {e:?}", table.name);
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response()
+}
+
+fn encode_rows(
+    format: Format,
+    table: &Table,
+    selected: &[usize],
+    data: &[u8],
+    rows: Range<usize>,
+    tx: &mpsc::Sender<anyhow::Result<Bytes>>,
+) -> anyhow::Result<()> {
+    let dat = Dat::parse(data)?;
+    let names = table.column_names();
+    let offsets = table.offsets();
+    if format == Format::Csv {
+        let mut out = Vec::new();
+        csv::Writer::from_writer(&mut out).write_record(selected.iter().map(|&i| &names[i]))?;
+        if tx.blocking_send(Ok(out.into())).is_err() {
+            return Ok(());
+        }
+    }
+
+    for start in rows.clone().step_by(EXPORT_CHUNK) {
+        let mut out = Vec::new();
+        let chunk = start..rows.end.min(start + EXPORT_CHUNK);
+        if format == Format::Csv {
+            let mut csv = csv::Writer::from_writer(&mut out);
+            for row in chunk {
+                let values = dat.read_columns(row, table, selected, &offsets)?;
+                csv.write_record(values.iter().map(csv_field))?;
+            }
+            csv.flush()?;
+        } else {
+            for row in chunk {
+                let values = dat.read_columns(row, table, selected, &offsets)?;
+                let row = NamedRow {
+                    names: &names,
+                    selected,
+                    values: &values,
+                };
+                serde_json::to_writer(&mut out, &row)?;
+                out.push(b'\n');
+            }
+        }
+        // The client went away, no point encoding the rest.
+        if tx.blocking_send(Ok(out.into())).is_err() {
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// A row serialized as an object keyed by column name, in schema order.
+struct NamedRow<'a> {
+    names: &'a [String],
+    selected: &'a [usize],
+    values: &'a [Value],
+}
+
+impl Serialize for NamedRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.values.len()))?;
+        for (&i, value) in self.selected.iter().zip(self.values) {
+            map.serialize_entry(&self.names[i], value)?;
+        }
+        map.end()
+    }
+}
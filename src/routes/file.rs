@@ -0,0 +1,127 @@
+use crate::index::state::{Fields, IndexState};
+use crate::routes::browse::resolve_storage;
+use crate::AppState;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use tantivy::collector::TopDocs;
+use tantivy::query::{BooleanQuery, Occur, TermQuery};
+use tantivy::schema::IndexRecordOption::Basic;
+use tantivy::schema::Value;
+use tantivy::{Searcher, TantivyDocument, Term};
+
+/// Finds the entry of type `typ` at `path` in the first of the given versions that has it, so the
+/// newest version wins when several do. Without versions, any version is searched.
+pub(crate) fn find(
+    searcher: &Searcher,
+    fields: &Fields,
+    urls: &[String],
+    path: &str,
+    typ: &str,
+) -> tantivy::Result<Option<TantivyDocument>> {
+    if urls.is_empty() {
+        return find_in(searcher, fields, None, path, typ);
+    }
+    for url in urls {
+        if let Some(doc) = find_in(searcher, fields, Some(url), path, typ)? {
+            return Ok(Some(doc));
+        }
+    }
+    Ok(None)
+}
+
+fn find_in(
+    searcher: &Searcher,
+    fields: &Fields,
+    version: Option<&str>,
+    path: &str,
+    typ: &str,
+) -> tantivy::Result<Option<TantivyDocument>> {
+    let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+    let mut query: Vec<(Occur, Box<dyn tantivy::query::Query>)> = Vec::with_capacity(4);
+    if let Some(version) = version {
+        query.push((
+            Occur::Must,
+            Box::new(TermQuery::new(fields.version_term(version), Basic)),
+        ));
+    }
+    for (field, value) in [
+        (fields.parent, parent),
+        (fields.name, name),
+        (fields.typ, typ),
+    ] {
+        query.push((
+            Occur::Must,
+            Box::new(TermQuery::new(Term::from_field_text(field, value), Basic)),
+        ));
+    }
+    let query = BooleanQuery::new(query);
+
+    let found = searcher.search(&query, &TopDocs::with_limit(1).order_by_score())?;
+    found
+        .first()
+        .map(|&(_, addr)| searcher.doc(addr))
+        .transpose()
+}
+
+/// Resolves the storage from `adapter` or `path` the same way browse does, and finds the entry.
+pub(crate) async fn lookup(
+    state: &AppState,
+    adapter: Option<String>,
+    path: String,
+    typ: &str,
+) -> Result<TantivyDocument, Response> {
+    let storages = state.storages().await;
+    let (_, urls, path) = resolve_storage(state, &storages, adapter, path).await;
+    let path = path.trim_start_matches('/').to_lowercase();
+    let IndexState { reader, fields, .. } = state.index;
+
+    find(&reader.searcher(), fields, &urls, &path, typ)
+        .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(|| failure(StatusCode::NOT_FOUND, format!("{path} not found")))
+}
+
+/// Extracts the contents of an indexed file from its bundle, through the shared bundle cache.
+pub(crate) async fn read(state: &AppState, doc: &TantivyDocument) -> Result<Vec<u8>, Response> {
+    let fields = &state.index.fields;
+    let version = doc_version(fields, doc)?;
+    state
+        .bundles
+        .read_doc(version, doc, fields)
+        .await
+        .map_err(|e| failure(StatusCode::BAD_GATEWAY, format!("{e:?}")))
+}
+
+/// The hash of a file's contents, if hashes were indexed.
+pub(crate) fn doc_hash(fields: &Fields, doc: &TantivyDocument) -> Option<u64> {
+    doc.get_first(fields.content_hash).and_then(|v| v.as_u64())
+}
+
+pub(crate) fn doc_version<'a>(
+    fields: &Fields,
+    doc: &'a TantivyDocument,
+) -> Result<&'a str, Response> {
+    doc.get_first(fields.version)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| failure(StatusCode::INTERNAL_SERVER_ERROR, "document has no version"))
+}
+
+pub(crate) fn doc_path(fields: &Fields, doc: &TantivyDocument) -> String {
+    let name = doc.get_first(fields.name).and_then(|v| v.as_str());
+    match doc.get_first(fields.parent).and_then(|v| v.as_str()) {
+        Some(parent) if !parent.is_empty() => format!("{parent}/{}", name.unwrap_or_default()),
+        _ => name.unwrap_or_default().to_string(),
+    }
+}
+
+#[derive(Serialize)]
+struct Failure {
+    error: String,
+}
+
+/// An error response with the same `{ "error": ... }` body as browse's errors.
+pub(crate) fn failure(status: StatusCode, message: impl std::fmt::Display) -> Response {
+    let error = message.to_string();
+    (status, Json(Failure { error })).into_response()
+}
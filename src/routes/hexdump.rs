@@ -0,0 +1,113 @@
+use crate::index::state::EntryType;
+use crate::routes::file::{failure, lookup, read};
+use crate::AppState;
+use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+const DEFAULT_LENGTH: usize = 4096;
+const MAX_LENGTH: usize = 65536;
+const ROW: usize = 16;
+
+#[derive(Deserialize)]
+pub struct Params {
+    adapter: Option<String>,
+    #[serde(default)]
+    path: String,
+    #[serde(default)]
+    offset: usize,
+    length: Option<usize>,
+    #[serde(default)]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct HexResponse {
+    offset: usize,
+    size: usize,
+    /// Offset of the next page, if the file continues past this one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next: Option<usize>,
+    bytes: Vec<u8>,
+}
+
+/// Returns a page of a file as a hex+ASCII dump, or as raw bytes with `json=true`.
+pub async fn handler(
+    Query(Params {
+        adapter,
+        path,
+        offset,
+        length,
+        json,
+    }): Query<Params>,
+    State(state): State<AppState>,
+) -> Result<Response, Response> {
+    let length = length.unwrap_or(DEFAULT_LENGTH);
+    if length == 0 || length > MAX_LENGTH {
+        return Err(failure(
+            StatusCode::BAD_REQUEST,
+            format!("length must be between 1 and {MAX_LENGTH} bytes"),
+        ));
+    }
+    let doc = lookup(&state, adapter, path, EntryType::FILE).await?;
+    let data = read(&state, &doc).await?;
+    if offset > data.len() {
+        return Err(failure(
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            format!(
+                "offset {offset} is past the end of the {} byte file",
+                data.len()
+            ),
+        ));
+    }
+    let end = data.len().min(offset + length);
+    let next = (end < data.len()).then_some(end);
+    let page = &data[offset..end];
+
+    if json {
+        return Ok(Json(HexResponse {
+            offset,
+            size: data.len(),
+            next,
+            bytes: page.to_vec(),
+        })
+        .into_response());
+    }
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        dump(offset, page),
+    )
+        .into_response())
+}
+
+/// Formats bytes as rows of 16 with their offset, hex values and printable ASCII.
+fn dump(offset: usize, bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(ROW) * 78);
+    for (i, row) in bytes.chunks(ROW).enumerate() {
+        let _ = write!(out, "{:08x}  ", offset + i * ROW);
+        for j in 0..ROW {
+            match row.get(j) {
+                Some(b) => {
+                    let _ = write!(out, "{b:02x} ");
+                }
+                None => out.push_str("   "),
+            }
+            if j == ROW / 2 - 1 {
+                out.push(' ');
+            }
+        }
+        out.push_str(" |");
+        out.extend(row.iter().map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        }));
+        out.push_str("|\n");
+    }
+    out
+}
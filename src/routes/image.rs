@@ -0,0 +1,177 @@
+use crate::formats::dds::{self, Dds, Header, Stored};
+use crate::index::state::{EntryType, IndexState};
+use crate::routes::file::{doc_path, doc_version, failure, find, lookup, read};
+use crate::AppState;
+use axum::body::Bytes;
+use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, RgbaImage};
+use lru::LruCache;
+use serde::Deserialize;
+use std::io::Cursor;
+use std::num::NonZeroUsize;
+use std::sync::{LazyLock, Mutex};
+use tantivy::schema::Value;
+use tantivy::{Searcher, TantivyDocument};
+
+/// Textures may point at other textures, which could in theory point back.
+const MAX_LINKS: usize = 4;
+
+/// Encoded sprites keyed by version and sprite path.
+static SPRITES: LazyLock<Mutex<LruCache<(String, String), Bytes>>> =
+    LazyLock::new(|| Mutex::new(LruCache::new(NonZeroUsize::new(2000).unwrap())));
+
+#[derive(Deserialize)]
+pub struct Params {
+    adapter: Option<String>,
+    #[serde(default)]
+    path: String,
+}
+
+pub async fn sprite_handler(
+    Query(Params { adapter, path }): Query<Params>,
+    State(state): State<AppState>,
+) -> Result<Response, Response> {
+    let IndexState { reader, fields, .. } = state.index;
+    let sprite = lookup(&state, adapter, path, EntryType::SPRITE).await?;
+    let version = doc_version(fields, &sprite)?.to_string();
+    let key = (version.clone(), doc_path(fields, &sprite));
+    if let Some(png) = SPRITES.lock().unwrap().get(&key).cloned() {
+        return Ok(png_response(png));
+    }
+
+    let (Some(sheet), Some(x), Some(y), Some(w), Some(h)) = (
+        sprite
+            .get_first(fields.sprite_sheet)
+            .and_then(|v| v.as_str()),
+        sprite.get_first(fields.sprite_x).and_then(|v| v.as_u64()),
+        sprite.get_first(fields.sprite_y).and_then(|v| v.as_u64()),
+        sprite.get_first(fields.sprite_w).and_then(|v| v.as_u64()),
+        sprite.get_first(fields.sprite_h).and_then(|v| v.as_u64()),
+    ) else {
+        return Err(failure(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "sprite is missing its sheet or bounds",
+        ));
+    };
+    let searcher = reader.searcher();
+    let sheet_doc = find(&searcher, fields, &[version], sheet, EntryType::FILE)
+        .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(|| failure(StatusCode::NOT_FOUND, format!("sheet {sheet} not found")))?;
+    let data = read_texture(&state, &searcher, sheet_doc).await?;
+
+    let png = tokio::task::spawn_blocking(move || {
+        let dds = Dds::parse(&data)?;
+        encode_png(&dds.decode_region(x as u32, y as u32, w as u32, h as u32)?)
+    })
+    .await
+    .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?
+    .map_err(|e| failure(StatusCode::UNPROCESSABLE_ENTITY, format!("{e:?}")))?;
+
+    let png = Bytes::from(png);
+    SPRITES.lock().unwrap().put(key, png.clone());
+    Ok(png_response(png))
+}
+
+#[derive(Deserialize)]
+pub struct TextureParams {
+    adapter: Option<String>,
+    #[serde(default)]
+    path: String,
+    max_size: Option<u32>,
+}
+
+pub async fn texture_handler(
+    Query(TextureParams {
+        adapter,
+        path,
+        max_size,
+    }): Query<TextureParams>,
+    State(state): State<AppState>,
+) -> Result<Response, Response> {
+    let IndexState { reader, .. } = state.index;
+    let doc = lookup(&state, adapter, path, EntryType::FILE).await?;
+    let data = read_texture(&state, &reader.searcher(), doc).await?;
+    let max_size = max_size.filter(|&max| max > 0);
+
+    let png = tokio::task::spawn_blocking(move || {
+        let header = Header::parse(&data)?;
+        // decode the smallest mip level that is still at least max_size, then scale it down to fit
+        let level = max_size
+            .and_then(|max| {
+                (0..header.mip_count)
+                    .take_while(|&l| {
+                        let (w, h) = header.dimensions(l);
+                        w.max(h) >= max
+                    })
+                    .last()
+            })
+            .unwrap_or(0);
+        let mut image = Dds::parse_mip(&data, level)?.decode()?;
+        if let Some(max) = max_size {
+            let (w, h) = image.dimensions();
+            if w.max(h) > max {
+                let scale = max as f64 / w.max(h) as f64;
+                let w = ((w as f64 * scale).round() as u32).max(1);
+                let h = ((h as f64 * scale).round() as u32).max(1);
+                image = imageops::resize(&image, w, h, FilterType::Triangle);
+            }
+        }
+        encode_png(&image)
+    })
+    .await
+    .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?
+    .map_err(|e| failure(StatusCode::UNPROCESSABLE_ENTITY, format!("{e:?}")))?;
+
+    Ok(png_response(Bytes::from(png)))
+}
+
+/// Reads a `.dds` file and unwraps it, following links to other textures.
+async fn read_texture(
+    state: &AppState,
+    searcher: &Searcher,
+    mut doc: TantivyDocument,
+) -> Result<Vec<u8>, Response> {
+    let fields = &state.index.fields;
+    for _ in 0..MAX_LINKS {
+        let data = read(state, &doc).await?;
+        let link = match dds::unwrap(data)
+            .map_err(|e| failure(StatusCode::UNPROCESSABLE_ENTITY, format!("{e:?}")))?
+        {
+            Stored::Dds(data) => return Ok(data),
+            Stored::Link(link) => link,
+        };
+        let version = doc_version(fields, &doc)?.to_string();
+        doc = find(searcher, fields, &[version], &link, EntryType::FILE)
+            .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?
+            .ok_or_else(|| {
+                failure(
+                    StatusCode::NOT_FOUND,
+                    format!("linked texture {link} not found"),
+                )
+            })?;
+    }
+    Err(failure(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "too many texture links",
+    ))
+}
+
+fn encode_png(image: &RgbaImage) -> anyhow::Result<Vec<u8>> {
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(png)
+}
+
+fn png_response(png: Bytes) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "image/png"),
+            (header::CACHE_CONTROL, "public, max-age=86400"),
+        ],
+        png,
+    )
+        .into_response()
+}
@@ -1,2 +1,14 @@
+pub mod audio;
 pub mod browse;
+pub mod dat;
+pub mod file;
+pub mod hexdump;
+pub mod image;
+pub mod model;
+pub mod preview;
+pub mod psg;
+pub mod references;
+pub mod sprites;
+pub mod stats;
+pub mod template;
 pub mod version;
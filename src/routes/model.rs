@@ -0,0 +1,106 @@
+use crate::formats::mesh::{Geometry, MeshInfo};
+use crate::formats::{gltf, references, text};
+use crate::index::state::{EntryType, IndexState};
+use crate::routes::file::{doc_version, failure, find, lookup, read};
+use crate::AppState;
+use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use tantivy::schema::Value;
+
+/// Extensions of the meshes that hold a single vertex buffer.
+const MODEL_EXT: &[&str] = &["fmt", "smd"];
+
+/// Suffixes and words naming the colour texture of a material.
+const COLOUR_TEXTURE: &[&str] = &["_c", "colour", "color", "albedo", "diffuse"];
+
+#[derive(Deserialize)]
+pub struct Params {
+    adapter: Option<String>,
+    #[serde(default)]
+    path: String,
+}
+
+/// Converts a static mesh into binary glTF.
+///
+/// The colour texture of the mesh's first material is referenced through `/texture.png`, relative
+/// to this route.
+pub async fn handler(
+    Query(Params { adapter, path }): Query<Params>,
+    State(state): State<AppState>,
+) -> Result<Response, Response> {
+    let IndexState { reader, fields, .. } = state.index;
+    let doc = lookup(&state, adapter.clone(), path, EntryType::FILE).await?;
+    let ext = doc
+        .get_first(fields.extension)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+    if !MODEL_EXT.contains(&ext.as_str()) {
+        return Err(failure(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "file is not a static mesh",
+        ));
+    }
+    let data = read(&state, &doc).await?;
+    let info = MeshInfo::parse(&ext, &data)
+        .map_err(|e| failure(StatusCode::UNPROCESSABLE_ENTITY, format!("{e:?}")))?;
+
+    let mut texture = None;
+    if let Some(material) = info.materials.first() {
+        let version = [doc_version(fields, &doc)?.to_string()];
+        let found = find(
+            &reader.searcher(),
+            fields,
+            &version,
+            material,
+            EntryType::FILE,
+        )
+        .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        if let Some(material) = found {
+            let data = read(&state, &material).await?;
+            texture = colour_texture(&text::decode(&data)).map(|path| {
+                let mut query = url::form_urlencoded::Serializer::new(String::new());
+                if let Some(adapter) = &adapter {
+                    query.append_pair("adapter", adapter);
+                }
+                query.append_pair("path", &path);
+                format!("texture.png?{}", query.finish())
+            });
+        }
+    }
+
+    let glb = tokio::task::spawn_blocking(move || {
+        Geometry::parse(&ext, &data).map(|geometry| gltf::encode(&geometry, texture.as_deref()))
+    })
+    .await
+    .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?
+    .map_err(|e| failure(StatusCode::UNPROCESSABLE_ENTITY, format!("{e:?}")))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "model/gltf-binary"),
+            (header::CACHE_CONTROL, "public, max-age=86400"),
+        ],
+        glb,
+    )
+        .into_response())
+}
+
+/// The first texture of a material that looks like its colour map.
+fn colour_texture(material: &str) -> Option<String> {
+    references::extract(material, "mat")
+        .into_iter()
+        .filter(|path| path.ends_with(".dds"))
+        .find(|path| {
+            let stem = path
+                .rsplit('/')
+                .next()
+                .unwrap_or(path)
+                .trim_end_matches(".dds");
+            COLOUR_TEXTURE
+                .iter()
+                .any(|word| stem.ends_with(word) || (word.len() > 2 && stem.contains(word)))
+        })
+}
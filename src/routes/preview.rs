@@ -0,0 +1,72 @@
+use crate::formats::text::{self, Encoding, TEXT_EXT};
+use crate::index::state::{EntryType, IndexState};
+use crate::routes::file::{failure, lookup, read};
+use crate::AppState;
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderName, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use tantivy::schema::Value;
+
+#[derive(Deserialize)]
+pub struct Params {
+    adapter: Option<String>,
+    #[serde(default)]
+    path: String,
+    /// First line to return, starting at 1.
+    start: Option<usize>,
+    /// Last line to return, inclusive.
+    end: Option<usize>,
+}
+
+/// Returns a text file converted to UTF-8, optionally limited to a range of lines.
+pub async fn handler(
+    Query(Params {
+        adapter,
+        path,
+        start,
+        end,
+    }): Query<Params>,
+    State(state): State<AppState>,
+) -> Result<Response, Response> {
+    let IndexState { fields, .. } = state.index;
+    let doc = lookup(&state, adapter, path, EntryType::FILE).await?;
+    let ext = doc.get_first(fields.extension).and_then(|v| v.as_str());
+    if !ext.is_some_and(|ext| TEXT_EXT.contains(&ext)) {
+        return Err(failure(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "file is not a text file",
+        ));
+    }
+    let data = read(&state, &doc).await?;
+    let (encoding, _) = Encoding::detect(&data);
+    let mut content = text::decode(&data);
+
+    if start.is_some() || end.is_some() {
+        let start = start.unwrap_or(1).max(1);
+        let end = end.unwrap_or(usize::MAX);
+        if end < start {
+            return Err(failure(
+                StatusCode::BAD_REQUEST,
+                format!("invalid line range {start}..{end}"),
+            ));
+        }
+        content = content
+            .split_inclusive('\n')
+            .skip(start - 1)
+            .take(end - start + 1)
+            .collect();
+    }
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+            (
+                HeaderName::from_static("x-source-encoding"),
+                encoding.name(),
+            ),
+        ],
+        content,
+    )
+        .into_response())
+}
@@ -0,0 +1,56 @@
+use crate::formats::psg::{Graph, PASSIVE_TREE};
+use crate::index::state::{EntryType, IndexState};
+use crate::routes::file::{doc_path, doc_version, failure, lookup, read};
+use crate::AppState;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct Params {
+    adapter: Option<String>,
+    /// Version URL to read the graph from, instead of any version of the storage.
+    version: Option<String>,
+    /// Defaults to the passive skill tree.
+    path: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct GraphResponse {
+    version: String,
+    path: String,
+    graph: Graph,
+}
+
+/// Returns a passive skill graph parsed into JSON.
+pub async fn handler(
+    Query(Params {
+        adapter,
+        version,
+        path,
+    }): Query<Params>,
+    State(state): State<AppState>,
+) -> Result<Json<GraphResponse>, Response> {
+    let IndexState { fields, .. } = state.index;
+    let path = path
+        .filter(|path| !path.is_empty())
+        .unwrap_or_else(|| PASSIVE_TREE.to_string());
+    let doc = lookup(&state, version.or(adapter), path, EntryType::FILE).await?;
+    let path = doc_path(fields, &doc);
+    if !path.ends_with(".psg") {
+        return Err(failure(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "file is not a passive skill graph",
+        ));
+    }
+    let data = read(&state, &doc).await?;
+    let graph = Graph::parse(&data)
+        .map_err(|e| failure(StatusCode::UNPROCESSABLE_ENTITY, format!("{e:?}")))?;
+    Ok(Json(GraphResponse {
+        version: doc_version(fields, &doc)?.to_string(),
+        path,
+        graph,
+    }))
+}
@@ -0,0 +1,102 @@
+use crate::index::state::{EntryType, IndexState};
+use crate::routes::browse::{
+    error, perform_query, process_doc, resolve_storage, sort_nodes, version_query, IndexResponse,
+};
+use crate::routes::file::{doc_path, doc_version, failure, find, lookup};
+use crate::AppState;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use tantivy::query::{BooleanQuery, Occur, TermQuery};
+use tantivy::schema::IndexRecordOption::Basic;
+use tantivy::schema::Value;
+use tantivy::Term;
+
+#[derive(Deserialize)]
+pub struct Params {
+    adapter: Option<String>,
+    #[serde(default)]
+    path: String,
+}
+
+#[derive(Serialize)]
+pub struct ReferencesResponse {
+    path: String,
+    references: Vec<Reference>,
+}
+
+#[derive(Serialize)]
+struct Reference {
+    path: String,
+    /// Whether the referenced file is in the same version; `false` marks a broken reference.
+    exists: bool,
+}
+
+/// Lists the files a text asset refers to.
+pub async fn handler(
+    Query(Params { adapter, path }): Query<Params>,
+    State(state): State<AppState>,
+) -> Result<Json<ReferencesResponse>, Response> {
+    let IndexState { reader, fields, .. } = state.index;
+    let doc = lookup(&state, adapter, path, EntryType::FILE).await?;
+    let version = [doc_version(fields, &doc)?.to_string()];
+    let searcher = reader.searcher();
+    let references = doc
+        .get_all(fields.references)
+        .filter_map(|v| v.as_str())
+        .map(|path| {
+            let found = find(&searcher, fields, &version, path, EntryType::FILE)
+                .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+            Ok(Reference {
+                path: path.to_string(),
+                exists: found.is_some(),
+            })
+        })
+        .collect::<Result<Vec<_>, Response>>()?;
+    Ok(Json(ReferencesResponse {
+        path: doc_path(fields, &doc),
+        references,
+    }))
+}
+
+/// Lists the files that refer to a path.
+pub async fn referenced_by_handler(
+    Query(Params { adapter, path }): Query<Params>,
+    State(state): State<AppState>,
+) -> Result<Json<IndexResponse>, Response> {
+    let storages = state.storages().await;
+    let (adapter, urls, path) = resolve_storage(&state, &storages, adapter, path).await;
+    let path = path.trim_start_matches('/').to_lowercase();
+    if path.is_empty() {
+        return Err(error("path is required".to_string(), &storages));
+    }
+
+    let IndexState { reader, fields, .. } = state.index;
+
+    let mut query: Vec<(Occur, Box<dyn tantivy::query::Query>)> = Vec::with_capacity(2);
+    if let Some(version_query) = version_query(fields, &urls) {
+        query.push((Occur::Must, version_query));
+    }
+    query.push((
+        Occur::Must,
+        Box::new(TermQuery::new(
+            Term::from_field_text(fields.references, path.as_str()),
+            Basic,
+        )),
+    ));
+    let query: Box<dyn tantivy::query::Query> = Box::new(BooleanQuery::new(query));
+
+    let mut files = perform_query(&reader.searcher(), &storages, query, None, |doc| {
+        process_doc(adapter.clone(), fields, doc)
+    })?;
+    sort_nodes(&mut files);
+
+    Ok(Json(IndexResponse {
+        adapter,
+        storages,
+        files,
+        debug_query: None,
+    }))
+}
@@ -0,0 +1,66 @@
+use crate::index::state::{EntryType, IndexState};
+use crate::routes::browse::{
+    error, perform_query, process_doc, resolve_storage, sort_nodes, version_query, IndexResponse,
+};
+use crate::AppState;
+use axum::extract::{Query, State};
+use axum::response::Response;
+use axum::Json;
+use serde::Deserialize;
+use tantivy::query::{BooleanQuery, Occur, TermQuery};
+use tantivy::schema::IndexRecordOption::Basic;
+use tantivy::Term;
+
+#[derive(Deserialize)]
+pub struct Params {
+    adapter: Option<String>,
+    #[serde(default)]
+    sheet: String,
+}
+
+/// Lists every sprite cut from the given sprite sheet.
+pub async fn handler(
+    Query(Params { adapter, sheet }): Query<Params>,
+    State(state): State<AppState>,
+) -> Result<Json<IndexResponse>, Response> {
+    let storages = state.storages().await;
+    let (adapter, urls, sheet) = resolve_storage(&state, &storages, adapter, sheet).await;
+    let sheet = sheet.trim_start_matches('/').to_lowercase();
+    if sheet.is_empty() {
+        return Err(error("sheet is required".to_string(), &storages));
+    }
+
+    let IndexState { reader, fields, .. } = state.index;
+
+    let mut query: Vec<(Occur, Box<dyn tantivy::query::Query>)> = Vec::with_capacity(3);
+    if let Some(version_query) = version_query(fields, &urls) {
+        query.push((Occur::Must, version_query));
+    }
+    query.push((
+        Occur::Must,
+        Box::new(TermQuery::new(
+            Term::from_field_text(fields.typ, EntryType::SPRITE),
+            Basic,
+        )),
+    ));
+    query.push((
+        Occur::Must,
+        Box::new(TermQuery::new(
+            Term::from_field_text(fields.sprite_sheet, sheet.as_str()),
+            Basic,
+        )),
+    ));
+    let query: Box<dyn tantivy::query::Query> = Box::new(BooleanQuery::new(query));
+
+    let mut files = perform_query(&reader.searcher(), &storages, query, None, |doc| {
+        process_doc(adapter.clone(), fields, doc)
+    })?;
+    sort_nodes(&mut files);
+
+    Ok(Json(IndexResponse {
+        adapter,
+        storages,
+        files,
+        debug_query: None,
+    }))
+}
@@ -0,0 +1,94 @@
+use crate::formats::stat_descriptions::{Description, StatDescriptions};
+use crate::formats::text;
+use crate::index::state::{EntryType, IndexState};
+use crate::routes::browse::{resolve_storage, version_query};
+use crate::routes::file::{doc_path, failure, lookup, read};
+use crate::AppState;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use tantivy::collector::TopDocs;
+use tantivy::query::{BooleanQuery, Occur, TermQuery};
+use tantivy::schema::IndexRecordOption::Basic;
+use tantivy::{TantivyDocument, Term};
+
+/// Stat ids like `local_display_socketed_gems_get_increased_area_level` show up in dozens of
+/// description files, but never in hundreds.
+const MAX_FILES: usize = 100;
+
+#[derive(Deserialize)]
+pub struct Params {
+    adapter: Option<String>,
+    #[serde(default)]
+    path: String,
+}
+
+/// Returns a stat description file parsed into JSON.
+pub async fn handler(
+    Query(Params { adapter, path }): Query<Params>,
+    State(state): State<AppState>,
+) -> Result<Response, Response> {
+    let doc = lookup(&state, adapter, path, EntryType::FILE).await?;
+    let descriptions = parse(read(&state, &doc).await?)?;
+    Ok(Json(descriptions).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct SearchParams {
+    adapter: Option<String>,
+    #[serde(default)]
+    id: String,
+}
+
+#[derive(Serialize)]
+struct SearchResult {
+    path: String,
+    descriptions: Vec<Description>,
+}
+
+/// Finds the descriptions of a stat id across every stat description file of the versions.
+pub async fn search_handler(
+    Query(SearchParams { adapter, id }): Query<SearchParams>,
+    State(state): State<AppState>,
+) -> Result<Response, Response> {
+    let IndexState { reader, fields, .. } = state.index;
+    let storages = state.storages().await;
+    let (_, urls, _) = resolve_storage(&state, &storages, adapter, String::new()).await;
+    let mut query: Vec<(Occur, Box<dyn tantivy::query::Query>)> = vec![(
+        Occur::Must,
+        Box::new(TermQuery::new(
+            Term::from_field_text(fields.stat_id, &id),
+            Basic,
+        )),
+    )];
+    if let Some(version_query) = version_query(fields, &urls) {
+        query.push((Occur::Must, version_query));
+    }
+
+    let searcher = reader.searcher();
+    let found = searcher
+        .search(
+            &BooleanQuery::new(query),
+            &TopDocs::with_limit(MAX_FILES).order_by_score(),
+        )
+        .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let mut results = Vec::with_capacity(found.len());
+    for (_, addr) in found {
+        let doc = searcher
+            .doc::<TantivyDocument>(addr)
+            .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        let descriptions = parse(read(&state, &doc).await?)?;
+        results.push(SearchResult {
+            path: doc_path(fields, &doc),
+            descriptions: descriptions.describing(&id).cloned().collect(),
+        });
+    }
+    Ok(Json(results).into_response())
+}
+
+fn parse(data: Vec<u8>) -> Result<StatDescriptions, Response> {
+    StatDescriptions::parse(&text::decode(&data))
+        .map_err(|e| failure(StatusCode::UNPROCESSABLE_ENTITY, format!("{e:?}")))
+}
@@ -0,0 +1,159 @@
+use crate::formats::template::{self, TEMPLATE_EXT};
+use crate::formats::text;
+use crate::index::state::{EntryType, IndexState};
+use crate::routes::file::{doc_path, doc_version, failure, find, lookup, read};
+use crate::AppState;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Inheritance chains are a handful deep; anything longer is a loop or a runaway.
+const MAX_TEMPLATES: usize = 64;
+/// Entries describing the template itself rather than something its children inherit.
+const OWN_ENTRIES: &[&str] = &["extends", "abstract", "version"];
+
+#[derive(Deserialize)]
+pub struct Params {
+    adapter: Option<String>,
+    #[serde(default)]
+    path: String,
+    /// Merge every template the file extends into the result.
+    #[serde(default)]
+    merged: bool,
+}
+
+#[derive(Serialize)]
+struct TemplateResponse {
+    path: String,
+    template: Map<String, Value>,
+    /// Every template merged into the result, base templates first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    inherits: Vec<String>,
+    /// Extended templates that are not in the version.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    missing: Vec<String>,
+}
+
+/// Returns an object template as JSON, optionally merged with the templates it extends.
+pub async fn handler(
+    Query(Params {
+        adapter,
+        path,
+        merged,
+    }): Query<Params>,
+    State(state): State<AppState>,
+) -> Result<Response, Response> {
+    let IndexState { reader, fields, .. } = state.index;
+    let doc = lookup(&state, adapter, path, EntryType::FILE).await?;
+    let path = doc_path(fields, &doc);
+    let ext = path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext)
+        .filter(|ext| TEMPLATE_EXT.contains(ext))
+        .ok_or_else(|| {
+            failure(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "file is not an object template",
+            )
+        })?;
+    let root = parse(read(&state, &doc).await?)?;
+    if !merged {
+        return Ok(Json(TemplateResponse {
+            path,
+            template: root,
+            inherits: Vec::new(),
+            missing: Vec::new(),
+        })
+        .into_response());
+    }
+
+    // load every ancestor first, then merge them depth first
+    let version = doc_version(fields, &doc)?.to_string();
+    let searcher = reader.searcher();
+    let mut templates = HashMap::new();
+    let mut missing = Vec::new();
+    let mut queue = template::parents(&root);
+    templates.insert(path.clone(), root);
+    while let Some(parent) = queue.pop() {
+        let parent_path = format!("{parent}.{ext}");
+        if templates.contains_key(&parent_path) || missing.contains(&parent_path) {
+            continue;
+        }
+        if templates.len() >= MAX_TEMPLATES {
+            return Err(failure(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("{path} extends more than {MAX_TEMPLATES} templates"),
+            ));
+        }
+        let found = find(
+            &searcher,
+            fields,
+            std::slice::from_ref(&version),
+            &parent_path,
+            EntryType::FILE,
+        )
+        .map_err(|e| failure(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        let Some(parent_doc) = found else {
+            missing.push(parent_path);
+            continue;
+        };
+        let parent = parse(read(&state, &parent_doc).await?)?;
+        queue.extend(template::parents(&parent));
+        templates.insert(parent_path, parent);
+    }
+
+    let mut inherits = Vec::new();
+    let template = resolve(&path, ext, &templates, &mut HashSet::new(), &mut inherits)
+        .map_err(|e| failure(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    inherits.retain(|p| *p != path);
+    Ok(Json(TemplateResponse {
+        path,
+        template,
+        inherits,
+        missing,
+    })
+    .into_response())
+}
+
+/// Merges the ancestors of the template at `path` and then the template itself.
+fn resolve(
+    path: &str,
+    ext: &str,
+    templates: &HashMap<String, Map<String, Value>>,
+    visiting: &mut HashSet<String>,
+    inherits: &mut Vec<String>,
+) -> anyhow::Result<Map<String, Value>> {
+    let Some(template) = templates.get(path) else {
+        return Ok(Map::new());
+    };
+    if !visiting.insert(path.to_string()) {
+        anyhow::bail!("{path} extends itself");
+    }
+    let mut merged = Map::new();
+    for parent in template::parents(template) {
+        let mut parent = resolve(
+            &format!("{parent}.{ext}"),
+            ext,
+            templates,
+            visiting,
+            inherits,
+        )?;
+        parent.retain(|key, _| !OWN_ENTRIES.contains(&key.as_str()));
+        template::merge(&mut merged, parent);
+    }
+    template::merge(&mut merged, template.clone());
+    visiting.remove(path);
+    if !inherits.iter().any(|p| p == path) {
+        inherits.push(path.to_string());
+    }
+    Ok(merged)
+}
+
+fn parse(data: Vec<u8>) -> Result<Map<String, Value>, Response> {
+    template::parse(&text::decode(&data))
+        .map_err(|e| failure(StatusCode::UNPROCESSABLE_ENTITY, format!("{e:?}")))
+}